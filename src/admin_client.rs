@@ -0,0 +1,48 @@
+use std::time::Duration;
+use crate::client_config::ClientConfig;
+use crate::client_error::ClientError;
+use crate::config::shutdown_mode::ShutdownMode;
+use crate::server_info::ServerInfo;
+use crate::session::Session;
+use crate::shutdown_report::ShutdownReport;
+
+/// Operations on the server itself rather than on tasks. Each call opens a connection of its own.
+#[derive(Debug)]
+pub struct AdminClient {
+    config: ClientConfig,
+}
+
+impl AdminClient {
+    pub fn new(config: ClientConfig) -> AdminClient {
+        AdminClient { config }
+    }
+
+    /// Asks the server to stop. `Drain` lets running tasks finish, `Now` aborts them.
+    pub fn shutdown(&self, mode: ShutdownMode) -> Result<ShutdownReport, ClientError> {
+        self.shutdown_with_grace_period(mode, None)
+    }
+
+    /// Like `shutdown`, but a draining server aborts whatever is still running after `grace_period`.
+    pub fn shutdown_with_grace_period(&self, mode: ShutdownMode, grace_period: Option<Duration>) -> Result<ShutdownReport, ClientError> {
+        let report = self.with_session(|session| session.shutdown_server(mode, grace_period))?;
+        if self.config.logging {
+            println!("SHUTDOWN response: {}. {}:{}", report, self.config.host, self.config.port);
+        }
+        Ok(report)
+    }
+
+    /// Returns the round-trip time of a `PING`.
+    pub fn ping(&self) -> Result<Duration, ClientError> {
+        self.with_session(Session::ping)
+    }
+
+    pub fn server_info(&self) -> Result<ServerInfo, ClientError> {
+        self.with_session(Session::server_info)
+    }
+
+    fn with_session<T>(&self, action: impl FnOnce(&mut Session) -> Result<T, ClientError>) -> Result<T, ClientError> {
+        let mut session = Session::open(&self.config)?;
+        let result = action(&mut session);
+        session.conclude(result)
+    }
+}
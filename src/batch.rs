@@ -0,0 +1,182 @@
+use std::collections::VecDeque;
+use std::io;
+use std::thread;
+use std::time::Instant;
+use crate::client_config::ClientConfig;
+use crate::client_error::{ClientError, TimeoutKind};
+use crate::config::feature::Feature;
+use crate::config::status::Status;
+use crate::lab1::matrix::Matrix;
+use crate::poll_policy::PollPolicy;
+use crate::request_error::RequestError;
+use crate::session::{ExecutionResult, Session, WaitOutcome};
+
+/// Many matrices submitted over one session, with the results handed out in the order the
+/// tasks finish rather than the order they were added.
+#[derive(Debug)]
+pub struct Batch {
+    config: ClientConfig,
+    items: Vec<(Matrix, i32)>,
+}
+
+/// A finished task: its index in the batch and what came of it.
+pub type BatchItem = (usize, Result<ExecutionResult, ClientError>);
+
+/// Iterator over `(index, result)` pairs, where `index` is what `Batch::add` returned.
+/// Each call to `next` polls the unfinished tasks until one of them completes.
+#[derive(Debug)]
+pub struct BatchResults {
+    session: Session,
+    poll_policy: PollPolicy,
+    /// `(index, task id, size)` of every task that has not been handed out yet.
+    pending: VecDeque<(usize, i64, usize)>,
+    ready: VecDeque<BatchItem>,
+    start: Instant,
+    sweeps: u32,
+}
+
+impl Batch {
+    /// In the poll policy of `config`, `max_attempts` counts sweeps over all unfinished tasks
+    /// and `ask_before_ready` is ignored.
+    pub fn new(config: ClientConfig) -> Batch {
+        Batch { config, items: Vec::new() }
+    }
+
+    /// Adds a matrix to be multiplied with `threads` threads and returns its index in the batch.
+    pub fn add(&mut self, matrix: Matrix, threads: i32) -> usize {
+        self.items.push((matrix, threads));
+        self.items.len() - 1
+    }
+
+    /// Posts and starts every task. Tasks the server refuses are reported through the iterator
+    /// like any other failure. If the connection is lost partway, the tasks that were not
+    /// submitted yet fail with the connection error and those already started are reported
+    /// with their ids once the iterator finds the session gone.
+    pub fn submit(self) -> Result<BatchResults, ClientError> {
+        let mut session = Session::open(&self.config)?;
+        let mut pending = VecDeque::with_capacity(self.items.len());
+        let mut ready = VecDeque::new();
+        let mut items = self.items.into_iter().enumerate();
+        while let Some((index, (matrix, threads))) = items.next() {
+            let submitted = session.post_task(&matrix, threads)
+                .and_then(|task_id| session.start_task(task_id).map(|_| task_id))
+                .map_err(|e| RequestError::wrap(&session, e));
+            match submitted {
+                Ok(task_id) => pending.push_back((index, task_id, matrix.size)),
+                Err(e) if e.is_connection_lost() => {
+                    let message = format!("Not submitted, connection lost: {}", e);
+                    ready.push_back((index, Err(e)));
+                    for (index, _) in items.by_ref() {
+                        let error = io::Error::new(io::ErrorKind::ConnectionAborted, message.clone());
+                        ready.push_back((index, Err(ClientError::Io(error))));
+                    }
+                    session.abort();
+                }
+                Err(e) => ready.push_back((index, Err(e))),
+            }
+        }
+        session.log(format_args!("Batch submitted: {} tasks, {} not submitted. {}", pending.len(), ready.len(), session));
+        Ok(BatchResults {
+            session,
+            poll_policy: self.config.poll_policy,
+            pending,
+            ready,
+            start: Instant::now(),
+            sweeps: 0,
+        })
+    }
+}
+
+impl BatchResults {
+    /// Checks every unfinished task once, moving finished and failed ones to `ready`.
+    fn sweep(&mut self) {
+        let pending: Vec<(usize, i64, usize)> = self.pending.drain(..).collect();
+        if self.session.supports(&Feature::Pipelining) {
+            let task_ids: Vec<i64> = pending.iter().map(|(_, task_id, _)| *task_id).collect();
+            let replies = match self.session.poll_many(&task_ids) {
+                Ok(replies) => replies,
+                Err(e) => return self.fail_all(pending, &e),
+            };
+            // The statuses were pipelined; finished tasks are downloaded one after another.
+            let mut finished = Vec::new();
+            for reply in replies {
+                let item = pending.iter().find(|(_, task_id, _)| *task_id == reply.task_id).copied();
+                match (item, reply.outcome) {
+                    (Some(item), Ok(task_status)) if task_status.status == Status::DONE => finished.push(item),
+                    (Some(item), Ok(task_status)) => self.settle(item, Ok(WaitOutcome::Pending(task_status))),
+                    (Some(item), Err(e)) => self.settle(item, Err(e)),
+                    (None, _) => {}
+                }
+            }
+            self.each(finished, |session, task_id, size| session.get_result(task_id, size).map(WaitOutcome::Done));
+            return;
+        }
+        self.each(pending, Session::poll);
+    }
+
+    /// Runs `request` for one task after another, until the connection is lost.
+    fn each<F>(&mut self, items: Vec<(usize, i64, usize)>, request: F)
+    where
+        F: Fn(&mut Session, i64, usize) -> Result<WaitOutcome, ClientError>,
+    {
+        let mut remaining = items.into_iter();
+        while let Some(item) = remaining.next() {
+            let outcome = request(&mut self.session, item.1, item.2)
+                .map_err(|e| RequestError::wrap(&self.session, e));
+            if let Err(e) = &outcome {
+                if e.is_connection_lost() {
+                    let rest = std::iter::once(item).chain(remaining).collect();
+                    self.fail_all(rest, e);
+                    return;
+                }
+            }
+            self.settle(item, outcome);
+        }
+    }
+
+    fn settle(&mut self, (index, task_id, size): (usize, i64, usize), outcome: Result<WaitOutcome, ClientError>) {
+        match outcome {
+            Ok(WaitOutcome::Done(result)) => self.ready.push_back((index, Ok(result))),
+            Ok(WaitOutcome::Pending(_)) => self.pending.push_back((index, task_id, size)),
+            Err(e) => self.ready.push_back((index, Err(e))),
+        }
+    }
+
+    /// The connection is gone, so none of the remaining tasks can be collected on it.
+    fn fail_all(&mut self, items: Vec<(usize, i64, usize)>, cause: &ClientError) {
+        for (index, task_id, _) in items {
+            let message = format!("task: {}. Connection lost: {}", task_id, cause);
+            let error = io::Error::new(io::ErrorKind::ConnectionAborted, message);
+            self.ready.push_back((index, Err(ClientError::Io(error))));
+        }
+        self.session.abort();
+    }
+}
+
+impl Iterator for BatchResults {
+    type Item = BatchItem;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.ready.pop_front() {
+                return Some(item);
+            }
+            if self.pending.is_empty() {
+                return None;
+            }
+            if self.sweeps > 0 {
+                if self.poll_policy.is_exhausted(self.sweeps, self.start.elapsed()) {
+                    let message = format!("Gave up waiting after {} sweeps in {:?}", self.sweeps, self.start.elapsed());
+                    for (index, task_id, _) in self.pending.drain(..) {
+                        let message = format!("task: {}. {}", task_id, message);
+                        self.ready.push_back((index, Err(ClientError::Timeout { kind: TimeoutKind::Poll, message })));
+                    }
+                    continue;
+                }
+                thread::sleep(self.poll_policy.delay(self.sweeps));
+            }
+            self.sweep();
+            self.sweeps += 1;
+        }
+    }
+}
@@ -1,69 +1,32 @@
-use crate::config::response_type::ResponseType;
+use crate::config::request_type::RequestType;
+use crate::protocol::{Capabilities, Response};
 use byteorder::{ReadBytesExt, BigEndian};
-use std::net::{TcpStream};
-use std::io::{BufRead, BufReader};
-
-pub(crate) struct BufferedReader<'a> {
-    pub reader: BufReader<&'a TcpStream>,
+use std::io::{BufReader, Read};
+
+#[derive(Debug)]
+pub(crate) struct BufferedReader<R: Read> {
+    pub reader: BufReader<R>,
+    /// Dialect the responses are decoded in; updated once `HELLO` has been answered.
+    pub capabilities: Capabilities,
+    /// Print matrix transfer progress and timings.
+    pub logging: bool,
 }
 
-impl<'a> BufferedReader<'a> {
-    pub(crate) fn new(reader: BufReader<&'a TcpStream>) -> Self {
-        BufferedReader { reader }
+impl<R: Read> BufferedReader<R> {
+    pub(crate) fn new(reader: BufReader<R>) -> Self {
+        BufferedReader { reader, capabilities: Capabilities::legacy(), logging: true }
     }
 
-    pub fn parse_long(&mut self, prefix: &str) -> i64 {
-        let read_line = self.read_line();
-        let substring = read_line.trim_start_matches(prefix);
-        let res = match substring.parse::<i64>() {
-            Ok(parsed_value) => parsed_value,
-            Err(e) => {
-                println!("{}", e);
-                return -1;
-            }
-        };
-        return res;
+    pub fn receive(&mut self, request: &RequestType) -> std::io::Result<Response> {
+        Response::decode(&mut self.reader, request, &self.capabilities)
     }
 
-    pub fn read_line(&mut self) -> String {
-        let mut read_line = String::new();
-        self.reader.read_line(&mut read_line).expect("Failed to read line");
-        let string = read_line.trim().to_string();
-        return string;
-    }
-
-    pub fn get_response_type(&mut self) -> Result<ResponseType, String> {
-        let mut line = String::new();
-        match self.reader.read_line(&mut line) {
-            Ok(bytes_read) => {
-                if bytes_read > 0 {
-                    if line.trim() == ResponseType::OK.to_string() {
-                        Ok(ResponseType::OK)
-                    } else {
-                        Ok(ResponseType::BadRequest)
-                    }
-                } else {
-                    Err(String::from("No data read from the stream."))
-                }
-            }
-            Err(e) => Err(e.to_string()),
-        }
+    pub fn receive_request_id(&mut self) -> std::io::Result<u64> {
+        Response::decode_request_id(&mut self.reader)
     }
 
-    pub fn read_error(&mut self) {
-        let mut line: String = String::new();
-        match self.reader.read_line(&mut line) {
-            Ok(bytes_read) => {
-                if bytes_read > 0 {
-                    eprintln!("Server {}", line)
-                } else {
-                    eprintln!("No error message")
-                }
-            }
-            Err(e) => {
-                eprintln!("{}", e.to_string())
-            }
-        }
+    pub fn receive_task_id(&mut self) -> std::io::Result<Response> {
+        Response::decode_task_id(&mut self.reader, &self.capabilities)
     }
 
     pub fn read_matrix(&mut self, size: usize, client: &str) -> std::io::Result<Vec<Vec<f64>>> {
@@ -76,13 +39,15 @@ impl<'a> BufferedReader<'a> {
                 let v = self.reader.read_f64::<BigEndian>()?;
                 double_row.push(v);
             }
-            if size >= 2000 && i % 1000 == 0 {
+            if self.logging && size >= 2000 && i % 1000 == 0 {
                 println!("Reading matrix of the size: {}, row: {}", size, i);
             }
             array.push(double_row);
         }
         let finish = start.elapsed().as_micros();
-        println!("Time to read: {} {}", finish, client);
+        if self.logging {
+            println!("Time to read: {} {}", finish, client);
+        }
         Ok(array)
     }
 }
@@ -1,30 +1,51 @@
-use std::error::Error;
-use std::io::{BufRead, BufReader};
-use std::net::{TcpStream};
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
 use std::{fmt};
-use std::str::FromStr;
-use crate::config::request_type::RequestType;
 use crate::lab1::matrix::Matrix;
-use crate::print_writer::Writer;
-use crate::buffered_reader::BufferedReader;
-use crate::prefix::{ID, SIZE, THREADS, TIME};
 use std::thread;
-use std::time::Duration;
-use crate::config::response_type::ResponseType::{BadRequest, OK};
-use crate::config::status::Status;
-use crate::custom_error::CustomError;
+use std::time::{Duration, Instant};
+use crate::client_config::ClientConfig;
+use crate::config::error_code::ErrorCode;
+use crate::client_error::ClientError;
+use crate::journal::{Journal, JournalEntry};
+use crate::config::feature::Feature;
+use crate::protocol::new_idempotency_key;
+use crate::task_handle::TaskHandle;
+use crate::task_status::TaskStatus;
+use crate::session::{ExecutionResult, Session, WaitOutcome};
 
-pub(crate) struct Client {
-    host: String,
-    port: u16,
-    size: i32,
+/// Runs the benchmark script for a single matrix: post, start, poll and fetch the result.
+/// The protocol itself is spoken by `Session`.
+#[derive(Debug)]
+pub struct Client {
     id: i32,
+    config: ClientConfig,
+    size: i32,
     thread_number: i32,
     task_id: i64,
+    /// Connection shared by the handles returned from `post_task`.
+    shared: Option<Arc<Mutex<Session>>>,
+}
+/// What one client asks the server to compute: a random matrix of `size` rows multiplied
+/// with `threads` threads.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TaskSpec {
+    pub size: i32,
+    pub threads: i32,
 }
 
-pub struct ExecutionResult {
-    matrix: Matrix,
+impl TaskSpec {
+    /// Catches sizes and thread counts no server can accept before anything is sent.
+    pub fn validate(&self) -> Result<(), ClientError> {
+        if self.size < 1 {
+            return Err(ClientError::InvalidTask(format!("Invalid matrix size: {}", self.size)));
+        }
+        if self.threads < 1 {
+            return Err(ClientError::InvalidTask(format!("Invalid number of threads: {}", self.threads)));
+        }
+        Ok(())
+    }
 }
 
 impl fmt::Display for Client {
@@ -39,232 +60,252 @@ impl fmt::Display for Client {
 
 
 impl Client {
-    pub(crate) fn new(host: &str, port: u16, size: i32, id: i32, thread_number: i32, task_id: i64) -> Client {
+    /// `id` only identifies the client in logs.
+    pub fn new(id: i32, config: ClientConfig, task: TaskSpec) -> Client {
         Client {
-            host: String::from(host),
-            port,
-            size,
             id,
-            thread_number,
-            task_id,
+            config,
+            size: task.size,
+            thread_number: task.threads,
+            task_id: -1,
+            shared: None,
+        }
+    }
+
+    fn log(&self, message: fmt::Arguments) {
+        if self.config.logging {
+            println!("{}", message);
         }
     }
 
     fn print_matrix(&self, message: &str, _matrix: &Matrix) {
-        println!("{}", message);
+        self.log(format_args!("{}", message));
         // _matrix.print();
     }
 
-    pub(crate) fn run(&mut self) {
-        match TcpStream::connect((self.host.as_str(), self.port)) {
-            Ok(stream) => {
-                let reader = BufReader::new(&stream);
-                let mut print_writer: Writer<&TcpStream> = Writer::new(&stream);
-                let mut buffered_reader: BufferedReader = BufferedReader::new(reader);
-                self.work(&mut buffered_reader, &mut print_writer);
-                /*
-                The &mut syntax is used to create a mutable reference.
-                It indicates that the function receiving the reference can mutate the value it refers to.
-                 By passing &mut buffered_reader and &mut print_writer to the work function,
-                 you are allowing that function to modify the buffered_reader and print_writer objects in the calling code.
-                */
-            }
-            Err(e) => {
-                eprintln!("Failed to connect: {}", e);
-            }
-        }
+    /// Fails without connecting if the task has an invalid size or thread count.
+    pub fn run(&mut self) -> Result<(), ClientError> {
+        self.task().validate()?;
+        self.with_session(Client::work)
     }
 
-    fn work(&mut self, reader: &mut BufferedReader, writer: &mut Writer<&TcpStream>) {
-        if self.size <= 1 {
-            println!("Killer client has been called");
-            self.shutdown_server(reader, writer);
-            return;
-        }
-        self.task_id = self.post_task(reader, writer);
-        if self.task_id < 0 {
-            println!("Server doesn't accept matrix. size: {}, threads: {}", self.size, self.thread_number);
-            return;
-        }
-        thread::sleep(Duration::from_millis(2));
-        let success_start: bool = self.start_task(reader, writer);
-        if !success_start {
-            println!("Failed to start. {}", self.to_string());
-            return;
-        }
-        println!("Successful start. {}", self.to_string());
-        thread::sleep(Duration::from_millis(1));
-        let mut result: Option<ExecutionResult>;
-        let mut i: i32 = 0;
-        loop {
-            result = self.get_status_or_result(reader, writer);
-            if result.is_none(){
-                println!("Result is not ready yet. {}", self.to_string());
-            }
-            thread::sleep(Duration::from_millis(1));
-            i += 1;
-            if result.is_some() || i >= 1 {//виставлено лише одну ітерацію, щоб мати можливість попросити результат який ще не готовий
-                break;
-            }
-        }
-        let result_matrix: Matrix;
-        if result.is_none() {
-            println!("The result is not ready, but the client asks for it {}", self.to_string());
-            result_matrix = self.request_result(reader, writer).unwrap().matrix;
-            /*
-            In Rust, the .unwrap() method is used to retrieve the value from an Option or Result type by unwrapping it.
-             It returns the inner value if it exists, or it will panic (throw runtime exception) if the value is None or if the Result is an Err variant.
-            */
-        } else {
-            result_matrix = result.unwrap().matrix;
-        }
-        self.print_matrix(format!("\nResult received. {}", self.to_string()).as_str(), &result_matrix);
+    /// Opens a session and hands it to `action`. The connection is closed when `action` returns.
+    fn with_session<T>(
+        &mut self,
+        action: impl FnOnce(&mut Client, &mut Session) -> Result<T, ClientError>,
+    ) -> Result<T, ClientError> {
+        let mut session = Session::open(&self.config).map_err(|e| {
+            eprintln!("Failed to connect: {}", e);
+            e
+        })?;
+        let result = action(self, &mut session);
+        /*
+        The &mut syntax is used to create a mutable reference.
+        It indicates that the function receiving the reference can mutate the value it refers to.
+         By passing &mut session to the action,
+         you are allowing that function to modify the session object in the calling code.
+        */
+        session.conclude(result)
     }
 
-    fn get_status_or_result(&self, reader: &mut BufferedReader, writer: &mut Writer<&TcpStream>) -> Option<ExecutionResult> {
-        let status = self.get_status(reader, writer).unwrap();
-        return match status {
-            Status::WAITING | Status::RUNNING => None,
-            Status::DONE => self.read_result(reader, writer)
-        };
+    /// Asks the server to abort a task. Opens a connection of its own, so it can be called
+    /// while another thread is still waiting for the task on the original connection.
+    pub fn cancel_task(&mut self, task_id: i64) -> Result<(), ClientError> {
+        self.with_session(|_, session| session.cancel_task(task_id))?;
+        self.journal(|journal, server| journal.record_failed(server, task_id, "cancelled"));
+        Ok(())
     }
 
-    fn get_status(&self, reader: &mut BufferedReader, writer: &mut Writer<&TcpStream>) -> Result<Status, Box<dyn Error>> {
-        writer.println_request(RequestType::GetTaskStatus);
-        writer.println(format!("{}{}", ID, self.task_id).as_str()).unwrap();
-        writer.println_end();
-        let response = reader.get_response_type();
-        match response {
-            Ok(response_type) => match response_type {
-                OK => {
-                    let line = reader.read_line();
-                    Ok(Status::from_str(&line).unwrap())
-                }
-                BadRequest => {
-                    reader.read_error();
-                    let error = CustomError { message: String::from("get_status error") };
-                    Err(Box::try_from(error).unwrap())
-                }
-            },
-            Err(e) => {
-                let message = format!("task: {}. IOException in getStatus().\n{}", self.task_id, e);
-                let error = CustomError::new(&message);
-                Err(Box::try_from(error).unwrap())
-            }
-        }
+    /// Posts and starts the task without waiting for it, and returns its id. With a journal
+    /// configured the task is recorded there, so its result can be fetched by a later run.
+    pub fn submit(&mut self) -> Result<i64, ClientError> {
+        self.task().validate()?;
+        self.with_session(|client, session| {
+            let matrix = Matrix::new(client.size as usize);
+            client.task_id = client.post(session, &matrix)?;
+            session.start_task(client.task_id)?;
+            client.journal_posted(&matrix);
+            client.log(format_args!("Task submitted. {} {}", client, session));
+            Ok(client.task_id)
+        })
     }
 
-    fn start_task(&self, reader: &mut BufferedReader, writer: &mut Writer<&TcpStream>) -> bool {
-        writer.println_request(RequestType::StartTask);
-        writer.println(format!("{}{}", ID, self.task_id).as_str()).unwrap();
-        writer.println_end();
-        let response = reader.get_response_type().unwrap();
-        return response != BadRequest;
+    /// Collects the result of a task a previous run posted, looking up its size in the journal
+    /// configured in `config`.
+    pub fn fetch(config: ClientConfig, task_id: i64) -> Result<ExecutionResult, ClientError> {
+        let path = config.journal.clone().ok_or_else(|| {
+            ClientError::Journal(io::Error::new(io::ErrorKind::NotFound, "No journal is configured"))
+        })?;
+        let journal = Journal::new(path);
+        let entry = journal.find_pending(&config.server(), task_id)
+            .map_err(ClientError::Journal)?
+            .ok_or_else(|| {
+                let message = format!("task {} on {} is not pending in {}", task_id, config.server(), journal.path().display());
+                ClientError::Journal(io::Error::new(io::ErrorKind::NotFound, message))
+            })?;
+        let mut client = Client::new(0, config, TaskSpec { size: entry.size as i32, threads: entry.threads });
+        client.await_result(task_id)
     }
 
-    fn post_task(&self, reader: &mut BufferedReader, writer: &mut Writer<&TcpStream>) -> i64 {
-        let matrix = Matrix::new(self.size as usize);
-        let id = self.id;
-        let size = self.size;
-        let message = format!("Client {id} created matrix of the size: {size}");
-        self.print_matrix(&message, &matrix);
-        return self.write_task(&matrix, reader, writer);
+    /// Blocks until the task is done or `timeout` elapses, using one `WAIT_FOR_RESULT` request
+    /// instead of repeated polling. Opens a connection of its own.
+    pub fn wait(&mut self, task_id: i64, timeout: Duration) -> Result<WaitOutcome, ClientError> {
+        self.task().validate()?;
+        self.task_id = task_id;
+        self.with_session(|client, session| session.wait(task_id, client.size as usize, timeout))
     }
 
-    fn shutdown_server(&self, reader: &mut BufferedReader, writer: &mut Writer<&TcpStream>) {
-        println!("Current client: {}", self.to_string());
-        writer.println_request(RequestType::SHUTDOWN);
-        writer.println_end();
-        let mut line = String::new();
-        match reader.reader.read_line(&mut line) {
-            Ok(bytes_read) => {
-                if bytes_read > 0 {
-                    // Process the line
-                    println!("SHUTDOWN response: {}", line.trim());
-                } else {
-                    println!("End of input");
-                }
-            }
-            Err(e) => {
-                eprintln!("Error reading line: {}", e);
-            }
-        }
+    /// Asks for the task's status once, with progress and queue position if the server sends them.
+    /// Never downloads the result, so it needs a server that negotiates `pipelining` during the
+    /// handshake; see `Session::status`. Opens a connection of its own.
+    pub fn get_status(&mut self, task_id: i64) -> Result<TaskStatus, ClientError> {
+        self.with_session(|_, session| session.status(task_id))
     }
 
+    /// Polls an already started task according to the poll policy and downloads its result.
+    /// Opens a connection of its own.
+    pub fn await_result(&mut self, task_id: i64) -> Result<ExecutionResult, ClientError> {
+        self.task().validate()?;
+        self.task_id = task_id;
+        self.with_session(|client, session| client.collect(session))
+    }
 
-    fn write_task(&self, matrix: &Matrix, reader: &mut BufferedReader, writer: &mut Writer<&TcpStream>) -> i64 {
-        let threads_header = format!("{}{}", THREADS, self.thread_number);
-        let size_header = format!("{}{}", SIZE, self.size);
-        writer.println_request(RequestType::PostNewTask);
-        writer.println(&threads_header).unwrap();
-        writer.println(&size_header).unwrap();
-        writer.println_end();
-        let result = reader.get_response_type();
-        return match result {
-            Ok(response_type) => {
-                return match response_type {
-                    OK => {
-                        if let Err(error) = writer.write_matrix(&matrix.data, self.to_string()) {
-                            println!("{}", error);
-                            return -1;
-                        }
-                        reader.parse_long(ID)
-                    }
-                    BadRequest => {
-                        reader.read_error();
-                        -1
-                    }
-                };
-            }
-            Err(error) => {
-                println!("{}", error);
-                -1
+    /// Posts a matrix with this client's thread count, starts it and returns a handle to the
+    /// running task. All handles of one client share a single connection, opened on first use.
+    pub fn post_task(&mut self, matrix: &Matrix) -> Result<TaskHandle, ClientError> {
+        let shared = match &self.shared {
+            Some(shared) => Arc::clone(shared),
+            None => {
+                let shared = Arc::new(Mutex::new(Session::open(&self.config)?));
+                self.shared = Some(Arc::clone(&shared));
+                shared
             }
         };
+        let mut session = shared.lock().map_err(|_| ClientError::Poisoned)?;
+        let posted = session.post_task(matrix, self.thread_number)
+            .and_then(|task_id| session.start_task(task_id).map(|_| task_id));
+        let task_id = session.conclude(posted)?;
+        drop(session);
+        self.log(format_args!("Task {} posted and started. {}", task_id, self));
+        Ok(TaskHandle::new(task_id, matrix.size, shared))
     }
-    fn read_result(&self, reader: &mut BufferedReader, writer: &mut Writer<&TcpStream>) -> Option<ExecutionResult> {
-        let response = reader.get_response_type().unwrap();
-        return match response {
-            OK => {
-                let execution_time = reader.parse_long(TIME);
-                println!("Downloading the result: {} executionTime: {}", self.to_string(), execution_time);
-                writer.println_response(OK);
-                let read: Vec<Vec<f64>> = reader.read_matrix(self.size as usize, self.to_string().as_str()).unwrap();
-                /*
-                In Rust, 'usize' is an unsigned integer type that represents the size of memory in bytes.
-                 It is platform-dependent, meaning its size depends on the architecture of the underlying system.
-                The usize type is commonly used for indexing and representing the size of collections,
-                 arrays, and memory allocations. It is guaranteed to be able to hold the size of the largest possible object
-                 that can be created on the current platform.
-                */
-                writer.println_response(OK);
-                return Some(
-                    ExecutionResult {
-                        matrix: Matrix {
-                            size: self.size as usize,
-                            data: read,
-                        },
-                    }
-                );
-            }
-            BadRequest => {
-                reader.read_error();
-                None
+
+    /// Posts the matrix. If retries are enabled and the server negotiated `idempotency`, the post
+    /// carries an idempotency key, and a post cut off by a lost connection is repeated with the
+    /// same key after reconnecting, as many times as the retry policy allows attempts after the
+    /// first, so the server doesn't end up with the task twice. Without the feature a lost post
+    /// is not repeated.
+    fn post(&mut self, session: &mut Session, matrix: &Matrix) -> Result<i64, ClientError> {
+        let repeatable = self.config.retry.is_enabled() && session.supports(&Feature::Idempotency);
+        let idempotency_key = repeatable.then(new_idempotency_key);
+        let max_repeats = self.config.retry.attempts.saturating_sub(1);
+        let mut repeats: u32 = 0;
+        loop {
+            match session.post_task_with_key(matrix, self.thread_number, idempotency_key.as_deref()) {
+                Err(e) if idempotency_key.is_some() && e.is_connection_lost() && repeats < max_repeats => {
+                    repeats += 1;
+                    eprintln!("Connection lost while posting, reconnecting ({} of {}). {} {} {}", repeats, max_repeats, self, session, e);
+                    thread::sleep(self.config.retry.delay(repeats));
+                    session.reconnect()?;
+                }
+                outcome => return outcome,
             }
-        };
+        }
     }
 
-    fn request_result(&self, reader: &mut BufferedReader, writer: &mut Writer<&TcpStream>) -> Option<ExecutionResult> {
-        writer.println_request(RequestType::GetResult);
-        writer.println(format!("{}{}", ID, self.task_id).as_str()).unwrap();
-        writer.println_end();
-        return self.read_result(reader, writer);
+    /// Polls for the result of the current task. If the connection is lost on the way, reconnects
+    /// up to `max_reconnects` times and carries on by task id; a download that was cut off
+    /// is started over with `GET_RESULT`. The poll policy's attempts and deadline cover all
+    /// sessions together.
+    fn collect(&mut self, session: &mut Session) -> Result<ExecutionResult, ClientError> {
+        let size = self.size as usize;
+        let mut reconnects: u32 = 0;
+        let start = Instant::now();
+        let mut attempts: u32 = 0;
+        loop {
+            let outcome = if session.interrupted_download() == Some(self.task_id) {
+                session.get_result(self.task_id, size)
+            } else {
+                session.await_result_since(self.task_id, size, &self.config.poll_policy, start, &mut attempts)
+            };
+            match outcome {
+                Err(e) if e.is_connection_lost() && reconnects < self.config.max_reconnects => {
+                    reconnects += 1;
+                    eprintln!("Connection lost, reconnecting ({} of {}). {} {} {}", reconnects, self.config.max_reconnects, self, session, e);
+                    session.reconnect()?;
+                }
+                outcome => {
+                    self.journal_outcome(&outcome);
+                    return outcome;
+                }
+            }
+        }
     }
-}
-
-
 
+    /// Only called once the task has started: a task that was posted but never started
+    /// has no result to fetch later.
+    fn journal_posted(&self, matrix: &Matrix) {
+        let entry = JournalEntry {
+            server: self.config.server(),
+            task_id: self.task_id,
+            size: matrix.size,
+            threads: self.thread_number,
+            submitted: SystemTime::now(),
+            fingerprint: matrix.fingerprint(),
+        };
+        self.journal(|journal, _| journal.record_posted(&entry));
+    }
 
+    /// Closes the task's journal entry once its result has been received or can never be.
+    fn journal_outcome(&self, outcome: &Result<ExecutionResult, ClientError>) {
+        let task_id = self.task_id;
+        match outcome.as_ref().map_err(ClientError::root) {
+            Ok(_) => self.journal(|journal, server| journal.record_collected(server, task_id)),
+            Err(e @ (ClientError::TaskFailed { .. } | ClientError::Cancelled { .. })) => {
+                self.journal(|journal, server| journal.record_failed(server, task_id, &e.to_string()))
+            }
+            Err(ClientError::ServerRejected(error)) if error.code == ErrorCode::UnknownTask => {
+                self.journal(|journal, server| journal.record_failed(server, task_id, &error.to_string()))
+            }
+            Err(_) => {}
+        }
+    }
 
+    /// A journal that can't be written doesn't fail the task; the id is still in the logs.
+    fn journal(&self, write: impl FnOnce(&Journal, &str) -> io::Result<()>) {
+        if let Some(path) = &self.config.journal {
+            let journal = Journal::new(path);
+            if let Err(e) = write(&journal, &self.config.server()) {
+                eprintln!("Failed to write the journal {}: {}. {}", path.display(), e, self);
+            }
+        }
+    }
 
+    fn task(&self) -> TaskSpec {
+        TaskSpec { size: self.size, threads: self.thread_number }
+    }
 
+    fn work(&mut self, session: &mut Session) -> Result<(), ClientError> {
+        let matrix = Matrix::new(self.size as usize);
+        let id = self.id;
+        let size = self.size;
+        let message = format!("Client {id} created matrix of the size: {size}");
+        self.print_matrix(&message, &matrix);
+        self.task_id = self.post(session, &matrix).map_err(|e| {
+            eprintln!("Server doesn't accept matrix. size: {}, threads: {}. {} {}", self.size, self.thread_number, session, e);
+            e
+        })?;
+        self.log(format_args!("Task posted. {} {}", self, session));
+        thread::sleep(Duration::from_millis(2));
+        if let Err(e) = session.start_task(self.task_id) {
+            eprintln!("Failed to start. {} {} {}", self, session, e);
+            return Err(e);
+        }
+        self.journal_posted(&matrix);
+        self.log(format_args!("Successful start. {} {}", self, session));
+        thread::sleep(Duration::from_millis(1));
+        let result = self.collect(session)?;
+        self.print_matrix(format!("\nResult received. {} executionTime: {}", self, result.execution_time).as_str(), &result.matrix);
+        Ok(())
+    }
+}
@@ -0,0 +1,326 @@
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+use crate::poll_policy::{Backoff, PollPolicy};
+use crate::retry_policy::RetryPolicy;
+
+/// Everything about how to reach and talk to the server, shared by all tasks of a run.
+/// Built with `ClientConfig::builder()`, which validates the options.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientConfig {
+    pub host: String,
+    pub port: u16,
+    pub connect_timeout: Option<Duration>,
+    pub read_timeout: Option<Duration>,
+    pub write_timeout: Option<Duration>,
+    /// Disables Nagle's algorithm, so short header lines are sent without waiting for more data.
+    pub nodelay: bool,
+    /// Idle time after which TCP keepalive probes are sent; no keepalive when unset.
+    pub keepalive: Option<Duration>,
+    /// `SO_SNDBUF` and `SO_RCVBUF`; the system defaults when unset.
+    pub send_buffer_size: Option<usize>,
+    pub recv_buffer_size: Option<usize>,
+    /// Open every connection with a `HELLO` exchange. Only for servers that understand it.
+    pub handshake: bool,
+    pub poll_policy: PollPolicy,
+    /// Applies to establishing connections, and to repeating a post the connection was lost
+    /// during when the server supports idempotency keys. Other requests are not repeated.
+    pub retry: RetryPolicy,
+    /// How many times `Client` reconnects to resume a started task after losing the connection.
+    pub max_reconnects: u32,
+    /// File in which `Client::run` and `Client::submit` record the tasks they post, so the
+    /// results can be fetched later with `Client::fetch`. See `Journal`.
+    pub journal: Option<PathBuf>,
+    /// Print progress messages to stdout. Errors go to stderr regardless.
+    pub logging: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ClientConfigBuilder {
+    host: Option<String>,
+    port: Option<u16>,
+    connect_timeout: Option<Duration>,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+    nodelay: Option<bool>,
+    keepalive: Option<Duration>,
+    send_buffer_size: Option<usize>,
+    recv_buffer_size: Option<usize>,
+    handshake: bool,
+    poll_policy: Option<PollPolicy>,
+    retry: Option<RetryPolicy>,
+    max_reconnects: u32,
+    journal: Option<PathBuf>,
+    logging: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    MissingAddress,
+    EmptyHost,
+    InvalidPort(u16),
+    /// A timeout of zero, which the socket API rejects.
+    ZeroTimeout(&'static str),
+    ZeroBufferSize(&'static str),
+    InvalidPollPolicy(String),
+    InvalidRetryPolicy(String),
+}
+
+impl ClientConfig {
+    pub fn builder() -> ClientConfigBuilder {
+        ClientConfigBuilder::default()
+    }
+
+    /// `host:port`, as used in logs and in the journal.
+    pub fn server(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+}
+
+impl ClientConfigBuilder {
+    pub fn address(mut self, host: &str, port: u16) -> Self {
+        self.host = Some(String::from(host));
+        self.port = Some(port);
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    pub fn write_timeout(mut self, timeout: Duration) -> Self {
+        self.write_timeout = Some(timeout);
+        self
+    }
+
+    /// On by default.
+    pub fn nodelay(mut self, nodelay: bool) -> Self {
+        self.nodelay = Some(nodelay);
+        self
+    }
+
+    pub fn keepalive(mut self, idle: Duration) -> Self {
+        self.keepalive = Some(idle);
+        self
+    }
+
+    pub fn send_buffer_size(mut self, size: usize) -> Self {
+        self.send_buffer_size = Some(size);
+        self
+    }
+
+    pub fn recv_buffer_size(mut self, size: usize) -> Self {
+        self.recv_buffer_size = Some(size);
+        self
+    }
+
+    pub fn handshake(mut self, handshake: bool) -> Self {
+        self.handshake = handshake;
+        self
+    }
+
+    pub fn poll_policy(mut self, poll_policy: PollPolicy) -> Self {
+        self.poll_policy = Some(poll_policy);
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// Off (zero) by default.
+    pub fn max_reconnects(mut self, max_reconnects: u32) -> Self {
+        self.max_reconnects = max_reconnects;
+        self
+    }
+
+    pub fn journal(mut self, path: impl Into<PathBuf>) -> Self {
+        self.journal = Some(path.into());
+        self
+    }
+
+    pub fn logging(mut self, logging: bool) -> Self {
+        self.logging = Some(logging);
+        self
+    }
+
+    pub fn build(self) -> Result<ClientConfig, ConfigError> {
+        let (host, port) = match (self.host, self.port) {
+            (Some(host), Some(port)) => (host, port),
+            _ => return Err(ConfigError::MissingAddress),
+        };
+        if host.trim().is_empty() {
+            return Err(ConfigError::EmptyHost);
+        }
+        if port == 0 {
+            return Err(ConfigError::InvalidPort(port));
+        }
+        for (name, timeout) in [
+            ("connect", self.connect_timeout),
+            ("read", self.read_timeout),
+            ("write", self.write_timeout),
+            ("keepalive", self.keepalive),
+        ] {
+            if timeout == Some(Duration::ZERO) {
+                return Err(ConfigError::ZeroTimeout(name));
+            }
+        }
+        for (name, size) in [("send", self.send_buffer_size), ("receive", self.recv_buffer_size)] {
+            if size == Some(0) {
+                return Err(ConfigError::ZeroBufferSize(name));
+            }
+        }
+        let poll_policy = self.poll_policy.unwrap_or_default();
+        validate_poll_policy(&poll_policy)?;
+        let retry = self.retry.unwrap_or_default();
+        validate_retry_policy(&retry)?;
+        Ok(ClientConfig {
+            host,
+            port,
+            connect_timeout: self.connect_timeout,
+            read_timeout: self.read_timeout,
+            write_timeout: self.write_timeout,
+            nodelay: self.nodelay.unwrap_or(true),
+            keepalive: self.keepalive,
+            send_buffer_size: self.send_buffer_size,
+            recv_buffer_size: self.recv_buffer_size,
+            handshake: self.handshake,
+            poll_policy,
+            retry,
+            max_reconnects: self.max_reconnects,
+            journal: self.journal,
+            logging: self.logging.unwrap_or(true),
+        })
+    }
+}
+
+fn validate_poll_policy(policy: &PollPolicy) -> Result<(), ConfigError> {
+    if policy.max_attempts == Some(0) {
+        return Err(ConfigError::InvalidPollPolicy(String::from("max attempts must be at least 1")));
+    }
+    if let Backoff::Exponential { initial, max } = policy.backoff {
+        if initial.is_zero() {
+            return Err(ConfigError::InvalidPollPolicy(String::from("initial backoff must not be zero")));
+        }
+        if max < initial {
+            let message = format!("backoff cap {:?} is below the initial delay {:?}", max, initial);
+            return Err(ConfigError::InvalidPollPolicy(message));
+        }
+    }
+    Ok(())
+}
+
+fn validate_retry_policy(policy: &RetryPolicy) -> Result<(), ConfigError> {
+    if policy.attempts == 0 {
+        return Err(ConfigError::InvalidRetryPolicy(String::from("attempts must be at least 1")));
+    }
+    if policy.max_delay < policy.base_delay {
+        let message = format!("delay cap {:?} is below the base delay {:?}", policy.max_delay, policy.base_delay);
+        return Err(ConfigError::InvalidRetryPolicy(message));
+    }
+    if !(0.0..=1.0).contains(&policy.jitter) {
+        let message = format!("jitter must be between 0 and 1, got {}", policy.jitter);
+        return Err(ConfigError::InvalidRetryPolicy(message));
+    }
+    Ok(())
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::MissingAddress => write!(f, "server address is not set"),
+            ConfigError::EmptyHost => write!(f, "server host is empty"),
+            ConfigError::InvalidPort(port) => write!(f, "invalid server port: {}", port),
+            ConfigError::ZeroTimeout(name) => write!(f, "{} timeout must be greater than zero", name),
+            ConfigError::ZeroBufferSize(name) => write!(f, "{} buffer size must be greater than zero", name),
+            ConfigError::InvalidPollPolicy(reason) => write!(f, "invalid poll policy: {}", reason),
+            ConfigError::InvalidRetryPolicy(reason) => write!(f, "invalid retry policy: {}", reason),
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builder() -> ClientConfigBuilder {
+        ClientConfig::builder().address("localhost", 8080)
+    }
+
+    #[test]
+    fn defaults() {
+        let config = builder().build().unwrap();
+        assert_eq!(config.server(), "localhost:8080");
+        assert!(config.nodelay);
+        assert!(config.logging);
+        assert!(!config.handshake);
+        assert_eq!(config.max_reconnects, 0);
+        assert_eq!(config.retry, RetryPolicy::none());
+        assert_eq!(config.poll_policy, PollPolicy::default());
+    }
+
+    #[test]
+    fn address_is_required() {
+        assert_eq!(ClientConfig::builder().build().unwrap_err(), ConfigError::MissingAddress);
+        assert_eq!(ClientConfig::builder().address(" ", 8080).build().unwrap_err(), ConfigError::EmptyHost);
+        assert_eq!(ClientConfig::builder().address("localhost", 0).build().unwrap_err(), ConfigError::InvalidPort(0));
+    }
+
+    #[test]
+    fn zero_timeouts_are_rejected() {
+        let cases = [
+            (builder().connect_timeout(Duration::ZERO), "connect"),
+            (builder().read_timeout(Duration::ZERO), "read"),
+            (builder().write_timeout(Duration::ZERO), "write"),
+            (builder().keepalive(Duration::ZERO), "keepalive"),
+        ];
+        for (builder, name) in cases {
+            assert_eq!(builder.build().unwrap_err(), ConfigError::ZeroTimeout(name));
+        }
+    }
+
+    #[test]
+    fn zero_buffer_sizes_are_rejected() {
+        assert_eq!(builder().send_buffer_size(0).build().unwrap_err(), ConfigError::ZeroBufferSize("send"));
+        assert_eq!(builder().recv_buffer_size(0).build().unwrap_err(), ConfigError::ZeroBufferSize("receive"));
+    }
+
+    #[test]
+    fn invalid_poll_policies_are_rejected() {
+        let policies = [
+            PollPolicy::default().with_max_attempts(0),
+            PollPolicy::exponential(Duration::ZERO, Duration::from_secs(1)),
+            PollPolicy::exponential(Duration::from_secs(2), Duration::from_secs(1)),
+        ];
+        for policy in policies {
+            let error = builder().poll_policy(policy.clone()).build().unwrap_err();
+            assert!(matches!(error, ConfigError::InvalidPollPolicy(_)), "accepted {:?}", policy);
+        }
+    }
+
+    #[test]
+    fn invalid_retry_policies_are_rejected() {
+        let second = Duration::from_secs(1);
+        let policies = [
+            RetryPolicy::exponential(0, second, second),
+            RetryPolicy::exponential(3, second * 2, second),
+            RetryPolicy::exponential(3, second, second).with_jitter(1.5),
+            RetryPolicy::exponential(3, second, second).with_jitter(-0.1),
+            RetryPolicy::exponential(3, second, second).with_jitter(f64::NAN),
+        ];
+        for policy in policies {
+            let error = builder().retry(policy.clone()).build().unwrap_err();
+            assert!(matches!(error, ConfigError::InvalidRetryPolicy(_)), "accepted {:?}", policy);
+        }
+    }
+}
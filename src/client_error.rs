@@ -0,0 +1,161 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use crate::config::feature::Feature;
+use crate::headers::HeaderError;
+use crate::protocol::ProtocolViolation;
+use crate::request_error::RequestError;
+use crate::server_error::ServerError;
+
+/// Everything that can go wrong while talking to the server.
+#[derive(Debug)]
+pub enum ClientError {
+    /// The connection could not be established.
+    Connect { server: String, source: io::Error },
+    /// The connection failed after it was established.
+    Io(io::Error),
+    /// The server sent something that makes no sense at this point of the conversation.
+    /// Carries a `ProtocolViolation` or a `HeaderError`.
+    Protocol(Box<dyn Error + Send + Sync>),
+    /// The server answered `BAD_REQUEST`.
+    ServerRejected(ServerError),
+    /// A socket timeout from the client configuration elapsed, or polling gave up
+    /// before the task was done.
+    Timeout { kind: TimeoutKind, message: String },
+    Cancelled { task_id: i64 },
+    /// The task failed on the server or its result expired before it was fetched.
+    TaskFailed { task_id: i64, reason: String },
+    /// The server didn't negotiate a feature the call needs.
+    Unsupported(Feature),
+    /// A task no server can accept, caught before anything was sent.
+    InvalidTask(String),
+    /// A thread panicked while holding the shared session.
+    Poisoned,
+    /// The task journal could not be read, or has no record of the task.
+    Journal(io::Error),
+    /// Any of the above, together with the request that was in flight.
+    Request(RequestError),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeoutKind {
+    Connect,
+    Read,
+    Write,
+    /// The poll policy's attempts or deadline ran out.
+    Poll,
+}
+
+impl ClientError {
+    /// Like `From<io::Error>`, but a timed out socket operation becomes a `Timeout` of `kind`.
+    pub fn from_io(error: io::Error, kind: TimeoutKind) -> ClientError {
+        match error.kind() {
+            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => {
+                ClientError::Timeout { kind, message: error.to_string() }
+            }
+            _ => ClientError::from(error),
+        }
+    }
+
+    /// The error without the request context around it.
+    pub fn root(&self) -> &ClientError {
+        match self {
+            ClientError::Request(error) => error.source.root(),
+            other => other,
+        }
+    }
+
+    /// Whether the stream is out of sync, so nothing read from it afterwards can be trusted.
+    pub fn is_protocol_violation(&self) -> bool {
+        match self.root() {
+            ClientError::Protocol(source) => source.is::<ProtocolViolation>(),
+            _ => false,
+        }
+    }
+
+    /// Whether nothing more can be read from the session the error happened on.
+    /// A socket timeout counts: it may have struck in the middle of a reply.
+    pub fn is_connection_lost(&self) -> bool {
+        match self.root() {
+            ClientError::Connect { .. } | ClientError::Io(_) | ClientError::Protocol(_) => true,
+            ClientError::Timeout { kind, .. } => *kind != TimeoutKind::Poll,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Connect { server, source } => write!(f, "Failed to connect to {}: {}", server, source),
+            ClientError::Io(source) => write!(f, "I/O error: {}", source),
+            ClientError::Protocol(source) => write!(f, "{}", source),
+            ClientError::ServerRejected(error) => write!(f, "{}", error),
+            ClientError::Timeout { kind, message } => write!(f, "{} timed out: {}", kind, message),
+            ClientError::Cancelled { task_id } => write!(f, "task: {}. Task was cancelled", task_id),
+            ClientError::TaskFailed { task_id, reason } => write!(f, "task: {}. {}", task_id, reason),
+            ClientError::Unsupported(feature) => write!(f, "Server doesn't support '{}'", feature),
+            ClientError::InvalidTask(message) => write!(f, "{}", message),
+            ClientError::Poisoned => write!(f, "Session was poisoned by a panicking thread"),
+            ClientError::Journal(source) => write!(f, "Journal error: {}", source),
+            ClientError::Request(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for ClientError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ClientError::Connect { source, .. } | ClientError::Io(source) | ClientError::Journal(source) => Some(source),
+            ClientError::Protocol(source) => Some(source.as_ref()),
+            ClientError::ServerRejected(error) => Some(error),
+            ClientError::Request(error) => error.source(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TimeoutKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeoutKind::Connect => write!(f, "Connect"),
+            TimeoutKind::Read => write!(f, "Read"),
+            TimeoutKind::Write => write!(f, "Write"),
+            TimeoutKind::Poll => write!(f, "Polling"),
+        }
+    }
+}
+
+/// Protocol errors travel up from the decoder inside `io::Error`s; they are unpacked here.
+impl From<io::Error> for ClientError {
+    fn from(error: io::Error) -> Self {
+        let is_protocol = error.get_ref()
+            .is_some_and(|inner| inner.is::<ProtocolViolation>() || inner.is::<HeaderError>());
+        if !is_protocol {
+            return ClientError::Io(error);
+        }
+        let kind = error.kind();
+        match error.into_inner() {
+            Some(inner) => ClientError::Protocol(inner),
+            None => ClientError::Io(io::Error::from(kind)),
+        }
+    }
+}
+
+impl From<ProtocolViolation> for ClientError {
+    fn from(error: ProtocolViolation) -> Self {
+        ClientError::Protocol(Box::new(error))
+    }
+}
+
+impl From<HeaderError> for ClientError {
+    fn from(error: HeaderError) -> Self {
+        ClientError::Protocol(Box::new(error))
+    }
+}
+
+impl From<ServerError> for ClientError {
+    fn from(error: ServerError) -> Self {
+        ClientError::ServerRejected(error)
+    }
+}
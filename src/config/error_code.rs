@@ -0,0 +1,45 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Machine-readable reason the server sends in the `error: ` header of a `BAD_REQUEST`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorCode {
+    SizeTooLarge,
+    InvalidThreads,
+    UnknownTask,
+    TaskNotReady,
+    ServerOverloaded,
+    InvalidRequest,
+    /// A code this client does not know yet, or a server that sends no code at all.
+    Other(String),
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorCode::SizeTooLarge => write!(f, "SIZE_TOO_LARGE"),
+            ErrorCode::InvalidThreads => write!(f, "INVALID_THREADS"),
+            ErrorCode::UnknownTask => write!(f, "UNKNOWN_TASK"),
+            ErrorCode::TaskNotReady => write!(f, "TASK_NOT_READY"),
+            ErrorCode::ServerOverloaded => write!(f, "SERVER_OVERLOADED"),
+            ErrorCode::InvalidRequest => write!(f, "INVALID_REQUEST"),
+            ErrorCode::Other(code) => write!(f, "{}", code),
+        }
+    }
+}
+
+impl FromStr for ErrorCode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "SIZE_TOO_LARGE" => Ok(ErrorCode::SizeTooLarge),
+            "INVALID_THREADS" => Ok(ErrorCode::InvalidThreads),
+            "UNKNOWN_TASK" => Ok(ErrorCode::UnknownTask),
+            "TASK_NOT_READY" => Ok(ErrorCode::TaskNotReady),
+            "SERVER_OVERLOADED" => Ok(ErrorCode::ServerOverloaded),
+            "INVALID_REQUEST" => Ok(ErrorCode::InvalidRequest),
+            other => Ok(ErrorCode::Other(other.to_string())),
+        }
+    }
+}
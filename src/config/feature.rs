@@ -0,0 +1,53 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Optional protocol extensions advertised in the `features: ` header of `HELLO`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Feature {
+    Compression,
+    Cancellation,
+    ExtendedStatus,
+    LongPoll,
+    /// Replies start with a `request-id: ` line, so requests can be sent without waiting for replies.
+    /// A `DONE` status is then no longer followed by the result, which is fetched with `GET_RESULT`.
+    Pipelining,
+    /// Requests may carry a `request-id: ` header, which is echoed in front of the reply.
+    RequestIds,
+    /// `POST_NEW_TASK` may carry an `idempotency-key: `; a repeated post with the same key
+    /// gets the id of the task created the first time.
+    Idempotency,
+    /// A feature this client does not know about; kept so it can be logged.
+    Other(String),
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Feature::Compression => write!(f, "compression"),
+            Feature::Cancellation => write!(f, "cancellation"),
+            Feature::ExtendedStatus => write!(f, "extended-status"),
+            Feature::LongPoll => write!(f, "long-poll"),
+            Feature::Pipelining => write!(f, "pipelining"),
+            Feature::RequestIds => write!(f, "request-ids"),
+            Feature::Idempotency => write!(f, "idempotency"),
+            Feature::Other(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl FromStr for Feature {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "compression" => Ok(Feature::Compression),
+            "cancellation" => Ok(Feature::Cancellation),
+            "extended-status" => Ok(Feature::ExtendedStatus),
+            "long-poll" => Ok(Feature::LongPoll),
+            "pipelining" => Ok(Feature::Pipelining),
+            "request-ids" => Ok(Feature::RequestIds),
+            "idempotency" => Ok(Feature::Idempotency),
+            other => Ok(Feature::Other(other.to_string())),
+        }
+    }
+}
@@ -1,13 +1,20 @@
 use std::fmt;
+use std::str::FromStr;
 
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum RequestType {
     PostNewTask,
     StartTask,
     GetTaskStatus,
     GetResult,
     SHUTDOWN,
+    Hello,
+    CancelTask,
+    WaitForResult,
+    Ping,
+    ServerInfo,
 }
 
 impl fmt::Display for RequestType {
@@ -18,7 +25,32 @@ impl fmt::Display for RequestType {
             RequestType::GetTaskStatus => write!(f, "GET_TASK_STATUS"),
             RequestType::GetResult => write!(f, "GET_RESULT"),
             RequestType::SHUTDOWN => write!(f, "SHUTDOWN"),
+            RequestType::Hello => write!(f, "HELLO"),
+            RequestType::CancelTask => write!(f, "CANCEL_TASK"),
+            RequestType::WaitForResult => write!(f, "WAIT_FOR_RESULT"),
+            RequestType::Ping => write!(f, "PING"),
+            RequestType::ServerInfo => write!(f, "SERVER_INFO"),
             // RequestType::BadRequest => write!(f, "BAD_REQUEST"),
         }
     }
 }
+
+impl FromStr for RequestType {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "POST_NEW_TASK" => Ok(RequestType::PostNewTask),
+            "START_TASK" => Ok(RequestType::StartTask),
+            "GET_TASK_STATUS" => Ok(RequestType::GetTaskStatus),
+            "GET_RESULT" => Ok(RequestType::GetResult),
+            "SHUTDOWN" => Ok(RequestType::SHUTDOWN),
+            "HELLO" => Ok(RequestType::Hello),
+            "CANCEL_TASK" => Ok(RequestType::CancelTask),
+            "WAIT_FOR_RESULT" => Ok(RequestType::WaitForResult),
+            "PING" => Ok(RequestType::Ping),
+            "SERVER_INFO" => Ok(RequestType::ServerInfo),
+            _ => Err(()),
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use std::fmt;
+use std::str::FromStr;
 
 #[derive(Debug, PartialEq)]
 pub enum ResponseType {
@@ -14,3 +15,15 @@ impl fmt::Display for ResponseType {
         }
     }
 }
+
+impl FromStr for ResponseType {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "OK" => Ok(ResponseType::OK),
+            "BAD_REQUEST" => Ok(ResponseType::BadRequest),
+            _ => Err(()),
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Sent in the `mode: ` header of `SHUTDOWN`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShutdownMode {
+    /// Stop accepting tasks and wait for the running ones, up to the grace period if one is given.
+    Drain,
+    /// Abort every task and stop right away.
+    Now,
+}
+
+impl fmt::Display for ShutdownMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShutdownMode::Drain => write!(f, "drain"),
+            ShutdownMode::Now => write!(f, "now"),
+        }
+    }
+}
+
+impl FromStr for ShutdownMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "drain" => Ok(ShutdownMode::Drain),
+            "now" => Ok(ShutdownMode::Now),
+            _ => Err(()),
+        }
+    }
+}
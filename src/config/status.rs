@@ -1,8 +1,29 @@
+use std::fmt;
 use std::str::FromStr;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum Status {
-    WAITING, RUNNING, DONE
+    WAITING, RUNNING, DONE, CANCELLED, EXPIRED,
+    /// Sent as `FAILED` or `FAILED: <reason>`.
+    FAILED(String),
+    /// A status introduced by a newer server; kept verbatim instead of failing the whole exchange.
+    Unknown(String),
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Status::WAITING => write!(f, "WAITING"),
+            Status::RUNNING => write!(f, "RUNNING"),
+            Status::DONE => write!(f, "DONE"),
+            Status::CANCELLED => write!(f, "CANCELLED"),
+            Status::EXPIRED => write!(f, "EXPIRED"),
+            Status::FAILED(reason) if reason.is_empty() => write!(f, "FAILED"),
+            Status::FAILED(reason) => write!(f, "FAILED: {}", reason),
+            Status::Unknown(status) => write!(f, "{}", status),
+        }
+    }
 }
 
 impl FromStr for Status {
@@ -10,10 +31,47 @@ impl FromStr for Status {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
+            "" => Err(()),
             "DONE" => Ok(Status::DONE),
             "RUNNING" => Ok(Status::RUNNING),
             "WAITING" => Ok(Status::WAITING),
-            _ => Err(()),
+            "CANCELLED" => Ok(Status::CANCELLED),
+            "EXPIRED" => Ok(Status::EXPIRED),
+            "FAILED" => Ok(Status::FAILED(String::new())),
+            _ => match s.strip_prefix("FAILED:") {
+                Some(reason) => Ok(Status::FAILED(reason.trim().to_string())),
+                None => Ok(Status::Unknown(s.to_string())),
+            },
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_known_statuses() {
+        for status in [Status::WAITING, Status::RUNNING, Status::DONE, Status::CANCELLED, Status::EXPIRED] {
+            assert_eq!(status.to_string().parse::<Status>(), Ok(status));
+        }
+    }
+
+    #[test]
+    fn failed_with_and_without_reason() {
+        assert_eq!("FAILED".parse::<Status>(), Ok(Status::FAILED(String::new())));
+        assert_eq!("FAILED: out of memory".parse::<Status>(), Ok(Status::FAILED(String::from("out of memory"))));
+        assert_eq!(Status::FAILED(String::from("out of memory")).to_string(), "FAILED: out of memory");
+        assert_eq!(Status::FAILED(String::new()).to_string(), "FAILED");
+    }
+
+    #[test]
+    fn empty_status_is_rejected() {
+        assert_eq!("".parse::<Status>(), Err(()));
+    }
+
+    #[test]
+    fn unknown_status_is_kept() {
+        assert_eq!("PAUSED".parse::<Status>(), Ok(Status::Unknown(String::from("PAUSED"))));
+    }
+}
@@ -0,0 +1,201 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+
+/// A block of `key: value` lines terminated by a blank line.
+/// Headers are addressed by the constants of the `prefix` module, e.g. `headers.require::<i64>(ID)`.
+/// Unknown headers are kept but ignored, so the server can add new ones without breaking the client.
+#[derive(Debug, Default, PartialEq)]
+pub struct Headers {
+    values: Vec<(String, String)>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum HeaderError {
+    /// A line inside the block is not of the form `key: value`.
+    InvalidLine(String),
+    /// The same header appears twice in one block.
+    Duplicate(String),
+    /// A required header is absent.
+    Missing(String),
+    /// The header is present but its value does not parse into the requested type.
+    Malformed { name: String, value: String },
+}
+
+impl Headers {
+    pub fn new() -> Self {
+        Headers { values: Vec::new() }
+    }
+
+    pub fn insert<T: fmt::Display>(&mut self, prefix: &str, value: T) -> &mut Self {
+        let name = key(prefix);
+        let value = value.to_string();
+        match self.values.iter_mut().find(|(existing, _)| *existing == name) {
+            Some(entry) => entry.1 = value,
+            None => self.values.push((name, value)),
+        }
+        self
+    }
+
+    pub fn get(&self, prefix: &str) -> Option<&str> {
+        let name = key(prefix);
+        self.values.iter()
+            .find(|(existing, _)| *existing == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn require<T: FromStr>(&self, prefix: &str) -> Result<T, HeaderError> {
+        self.optional(prefix)?.ok_or_else(|| HeaderError::Missing(key(prefix)))
+    }
+
+    pub fn optional<T: FromStr>(&self, prefix: &str) -> Result<Option<T>, HeaderError> {
+        match self.get(prefix) {
+            None => Ok(None),
+            Some(value) => value.parse::<T>().map(Some).map_err(|_| HeaderError::Malformed {
+                name: key(prefix),
+                value: value.to_string(),
+            }),
+        }
+    }
+
+    /// Reads lines up to and including the blank terminator.
+    pub fn read<R: BufRead>(input: &mut R) -> io::Result<Headers> {
+        let mut headers = Headers::new();
+        headers.read_into(input)?;
+        Ok(headers)
+    }
+
+    /// Like `read`, but continues a block whose first lines were already consumed with `push_line`.
+    pub fn read_into<R: BufRead>(&mut self, input: &mut R) -> io::Result<()> {
+        loop {
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Stream ended inside a header block."));
+            }
+            let line = line.trim();
+            if line.is_empty() {
+                return Ok(());
+            }
+            self.push_line(line)?;
+        }
+    }
+
+    pub fn push_line(&mut self, line: &str) -> Result<(), HeaderError> {
+        let (name, value) = line.split_once(':')
+            .ok_or_else(|| HeaderError::InvalidLine(line.to_string()))?;
+        let name = key(name);
+        if self.get(&name).is_some() {
+            return Err(HeaderError::Duplicate(name));
+        }
+        self.values.push((name, value.trim().to_string()));
+        Ok(())
+    }
+
+    /// Writes every header followed by the blank terminator.
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        self.write_lines(out)?;
+        writeln!(out)
+    }
+
+    /// Writes every header without the terminator, for replies of version 1 servers.
+    pub fn write_lines<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        for (name, value) in &self.values {
+            writeln!(out, "{}: {}", name, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Turns a prefix constant such as `"execution-time: "` into the bare, lower-case header name.
+fn key(prefix: &str) -> String {
+    prefix.trim().trim_end_matches(':').trim().to_ascii_lowercase()
+}
+
+impl fmt::Display for HeaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeaderError::InvalidLine(line) => write!(f, "invalid header line: '{}'", line),
+            HeaderError::Duplicate(name) => write!(f, "duplicate header: '{}'", name),
+            HeaderError::Missing(name) => write!(f, "missing header: '{}'", name),
+            HeaderError::Malformed { name, value } => write!(f, "malformed header '{}': '{}'", name, value),
+        }
+    }
+}
+
+impl Error for HeaderError {}
+
+impl From<HeaderError> for io::Error {
+    fn from(error: HeaderError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use super::*;
+    use crate::prefix::{ID, SIZE, TIME};
+
+    fn read(block: &str) -> io::Result<Headers> {
+        Headers::read(&mut Cursor::new(block.as_bytes()))
+    }
+
+    fn header_error(error: io::Error) -> HeaderError {
+        let error = error.into_inner().expect("a header error").downcast::<HeaderError>().expect("a header error");
+        *error
+    }
+
+    #[test]
+    fn reads_block_up_to_blank_line() {
+        let mut input = Cursor::new(b"id: 7\nSize:  1000 \nx-new: ignored\n\nOK\n".to_vec());
+        let headers = Headers::read(&mut input).unwrap();
+        assert_eq!(headers.require::<i64>(ID).unwrap(), 7);
+        assert_eq!(headers.require::<usize>(SIZE).unwrap(), 1000);
+        let mut rest = String::new();
+        input.read_line(&mut rest).unwrap();
+        assert_eq!(rest, "OK\n");
+    }
+
+    #[test]
+    fn write_then_read_round_trips() {
+        let mut headers = Headers::new();
+        headers.insert(ID, 7).insert(TIME, 1234);
+        let mut out = Vec::new();
+        headers.write(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out.clone()).unwrap(), "id: 7\nexecution-time: 1234\n\n");
+        assert_eq!(Headers::read(&mut Cursor::new(out)).unwrap(), headers);
+    }
+
+    #[test]
+    fn duplicate_header_is_rejected() {
+        let error = read("id: 7\nID: 8\n\n").unwrap_err();
+        assert_eq!(header_error(error), HeaderError::Duplicate(String::from("id")));
+    }
+
+    #[test]
+    fn line_without_colon_is_rejected() {
+        let error = read("id 7\n\n").unwrap_err();
+        assert_eq!(header_error(error), HeaderError::InvalidLine(String::from("id 7")));
+    }
+
+    #[test]
+    fn missing_header_is_reported_by_name() {
+        let headers = read("size: 10\n\n").unwrap();
+        assert_eq!(headers.optional::<i64>(ID).unwrap(), None);
+        assert_eq!(headers.require::<i64>(ID).unwrap_err(), HeaderError::Missing(String::from("id")));
+    }
+
+    #[test]
+    fn malformed_value_is_reported_with_value() {
+        let headers = read("id: seven\n\n").unwrap();
+        let expected = HeaderError::Malformed { name: String::from("id"), value: String::from("seven") };
+        assert_eq!(headers.require::<i64>(ID).unwrap_err(), expected);
+    }
+
+    #[test]
+    fn unterminated_block_is_unexpected_eof() {
+        let error = read("id: 7\n").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,239 @@
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Append-only record of posted tasks, so results can be collected by a later run.
+///
+/// Every line is a tab-separated record starting with a verb:
+/// `posted <server> <task id> <size> <threads> <submitted, unix seconds> <fingerprint>`,
+/// `collected <server> <task id>` or `failed <server> <task id> <reason>`.
+/// A task is pending until a `collected` or `failed` record follows its `posted` one.
+/// Each record is written with a single append, so clients of one process can share the file.
+#[derive(Debug)]
+pub struct Journal {
+    path: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JournalEntry {
+    pub server: String,
+    pub task_id: i64,
+    pub size: usize,
+    pub threads: i32,
+    pub submitted: SystemTime,
+    /// `Matrix::fingerprint` of the input.
+    pub fingerprint: u64,
+}
+
+enum Record {
+    Posted(JournalEntry),
+    Closed { server: String, task_id: i64 },
+}
+
+impl Journal {
+    /// The file is created on the first write.
+    pub fn new(path: impl AsRef<Path>) -> Journal {
+        Journal { path: path.as_ref().to_path_buf() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn record_posted(&self, entry: &JournalEntry) -> io::Result<()> {
+        let submitted = entry.submitted.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        self.append(&format!(
+            "posted\t{}\t{}\t{}\t{}\t{}\t{:016x}",
+            entry.server, entry.task_id, entry.size, entry.threads, submitted, entry.fingerprint
+        ))
+    }
+
+    pub fn record_collected(&self, server: &str, task_id: i64) -> io::Result<()> {
+        self.append(&format!("collected\t{}\t{}", server, task_id))
+    }
+
+    /// For tasks whose result will never be available, e.g. cancelled or failed ones.
+    pub fn record_failed(&self, server: &str, task_id: i64, reason: &str) -> io::Result<()> {
+        let reason = reason.replace(['\t', '\n', '\r'], " ");
+        self.append(&format!("failed\t{}\t{}\t{}", server, task_id, reason))
+    }
+
+    /// Tasks posted but neither collected nor failed, oldest first.
+    pub fn pending(&self) -> io::Result<Vec<JournalEntry>> {
+        let mut pending: Vec<JournalEntry> = Vec::new();
+        for record in self.read()? {
+            match record {
+                Record::Posted(entry) => pending.push(entry),
+                Record::Closed { server, task_id } => {
+                    pending.retain(|entry| entry.server != server || entry.task_id != task_id);
+                }
+            }
+        }
+        Ok(pending)
+    }
+
+    /// The pending task with this id on `server`.
+    pub fn find_pending(&self, server: &str, task_id: i64) -> io::Result<Option<JournalEntry>> {
+        Ok(self.pending()?.into_iter().find(|entry| entry.server == server && entry.task_id == task_id))
+    }
+
+    fn append(&self, line: &str) -> io::Result<()> {
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        file.write_all(format!("{}\n", line).as_bytes())
+    }
+
+    fn read(&self) -> io::Result<Vec<Record>> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut records = Vec::new();
+        for (number, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record = parse_record(&line).ok_or_else(|| {
+                let message = format!("{}:{}: malformed journal record '{}'", self.path.display(), number + 1, line);
+                io::Error::new(io::ErrorKind::InvalidData, message)
+            })?;
+            records.push(record);
+        }
+        Ok(records)
+    }
+}
+
+fn parse_record(line: &str) -> Option<Record> {
+    let fields: Vec<&str> = line.split('\t').collect();
+    match fields.as_slice() {
+        ["posted", server, task_id, size, threads, submitted, fingerprint] => Some(Record::Posted(JournalEntry {
+            server: server.to_string(),
+            task_id: parse(task_id)?,
+            size: parse(size)?,
+            threads: parse(threads)?,
+            submitted: UNIX_EPOCH + Duration::from_secs(parse(submitted)?),
+            fingerprint: u64::from_str_radix(fingerprint, 16).ok()?,
+        })),
+        ["collected", server, task_id] | ["failed", server, task_id, ..] => Some(Record::Closed {
+            server: server.to_string(),
+            task_id: parse(task_id)?,
+        }),
+        _ => None,
+    }
+}
+
+fn parse<T: FromStr>(field: &str) -> Option<T> {
+    field.parse().ok()
+}
+
+impl fmt::Display for JournalEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let age = SystemTime::now().duration_since(self.submitted).unwrap_or_default();
+        write!(
+            f,
+            "task {} on {}: size={}, threads={}, submitted {}s ago, fingerprint={:016x}",
+            self.task_id, self.server, self.size, self.threads, age.as_secs(), self.fingerprint
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use super::*;
+
+    /// A journal in the temp directory, removed when the test is done.
+    struct TempJournal(Journal);
+
+    impl TempJournal {
+        fn new(name: &str) -> TempJournal {
+            let path = std::env::temp_dir().join(format!("journal-{}-{}", std::process::id(), name));
+            let _ = fs::remove_file(&path);
+            TempJournal(Journal::new(path))
+        }
+    }
+
+    impl Drop for TempJournal {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(self.0.path());
+        }
+    }
+
+    fn entry(server: &str, task_id: i64) -> JournalEntry {
+        JournalEntry {
+            server: server.to_string(),
+            task_id,
+            size: 100,
+            threads: 4,
+            submitted: UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+            fingerprint: 0x0123456789abcdef,
+        }
+    }
+
+    #[test]
+    fn parses_posted_record() {
+        let line = "posted\tlocalhost:8080\t7\t100\t4\t1700000000\t0123456789abcdef";
+        match parse_record(line) {
+            Some(Record::Posted(parsed)) => assert_eq!(parsed, entry("localhost:8080", 7)),
+            _ => panic!("expected a posted record"),
+        }
+    }
+
+    #[test]
+    fn parses_closing_records() {
+        for line in ["collected\tlocalhost:8080\t7", "failed\tlocalhost:8080\t7\tcancelled"] {
+            match parse_record(line) {
+                Some(Record::Closed { server, task_id }) => assert_eq!((server.as_str(), task_id), ("localhost:8080", 7)),
+                _ => panic!("expected a closing record for '{}'", line),
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_records() {
+        for line in [
+            "posted\tlocalhost:8080\t7\t100\t4\t1700000000",
+            "posted\tlocalhost:8080\tseven\t100\t4\t1700000000\t0123456789abcdef",
+            "posted\tlocalhost:8080\t7\t100\t4\t1700000000\tnot-hex",
+            "collected\tlocalhost:8080",
+            "forgotten\tlocalhost:8080\t7",
+        ] {
+            assert!(parse_record(line).is_none(), "accepted '{}'", line);
+        }
+    }
+
+    #[test]
+    fn missing_file_has_nothing_pending() {
+        let journal = TempJournal::new("missing");
+        assert!(journal.0.pending().unwrap().is_empty());
+    }
+
+    #[test]
+    fn pending_skips_collected_and_failed_tasks() {
+        let journal = TempJournal::new("pending");
+        let journal = &journal.0;
+        for task_id in 1..=3 {
+            journal.record_posted(&entry("a:1", task_id)).unwrap();
+        }
+        journal.record_posted(&entry("b:2", 1)).unwrap();
+        journal.record_collected("a:1", 1).unwrap();
+        journal.record_failed("a:1", 3, "task: 3.\tcancelled\n").unwrap();
+        assert_eq!(journal.pending().unwrap(), vec![entry("a:1", 2), entry("b:2", 1)]);
+        assert_eq!(journal.find_pending("b:2", 1).unwrap(), Some(entry("b:2", 1)));
+        assert_eq!(journal.find_pending("a:1", 1).unwrap(), None);
+    }
+
+    #[test]
+    fn malformed_line_fails_with_its_number() {
+        let journal = TempJournal::new("malformed");
+        journal.0.record_collected("a:1", 1).unwrap();
+        OpenOptions::new().append(true).open(journal.0.path()).unwrap().write_all(b"garbage\n").unwrap();
+        let error = journal.0.pending().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains(":2:"), "{}", error);
+    }
+}
@@ -1,7 +1,7 @@
 use rand::Rng;
 
-pub(crate) struct Matrix {
-    #[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct Matrix {
     pub size: usize,
     pub data: Vec<Vec<f64>>,
 }
@@ -9,7 +9,7 @@ pub(crate) struct Matrix {
 impl Matrix {
     const MAX: f64 = 99.0;
 
-    pub(crate) fn new(size: usize) -> Self {
+    pub fn new(size: usize) -> Self {
         let mut rng = rand::thread_rng();
         let data = (0..size)
             .map(|_| {
@@ -22,14 +22,27 @@ impl Matrix {
         Matrix { size, data }
     }
 
-    #[allow(dead_code)]
-    fn from_data(data: Vec<Vec<f64>>) -> Self {
+    /// The rows must all be as long as there are rows; a matrix that isn't square is refused
+    /// when it is posted.
+    pub fn from_data(data: Vec<Vec<f64>>) -> Self {
         let size = data.len();
         Matrix { size, data }
     }
 
-    #[allow(dead_code)]
-    pub(crate) fn print(&self) {
+    /// FNV-1a hash of the values as big-endian bytes, the way they are sent to the server.
+    /// Stable across runs and platforms, so it can be stored to recognise the input later.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for value in self.data.iter().flatten() {
+            for byte in value.to_be_bytes() {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        }
+        hash
+    }
+
+    pub fn print(&self) {
         let size = std::cmp::min(self.size, 4);
         for i in 0..size {
             for j in 0..size {
@@ -0,0 +1,80 @@
+//! Client for the matrix task server: post a matrix, start the task, poll its status and
+//! download the result. `Client` runs the one-matrix benchmark script, `Session` speaks the
+//! protocol over a single connection, and `Batch` and `TaskHandle` manage many tasks at once.
+//! Server administration (`PING`, `SERVER_INFO`, `SHUTDOWN`) goes through `AdminClient`.
+
+pub mod config {
+    pub mod request_type;
+    pub mod response_type;
+    pub mod status;
+    pub mod error_code;
+    pub mod feature;
+    pub mod shutdown_mode;
+}
+
+pub mod lab1 {
+    pub mod matrix;
+}
+
+pub mod client;
+pub mod admin_client;
+pub mod client_config;
+mod print_writer;
+mod buffered_reader;
+pub mod client_error;
+pub mod server_error;
+pub mod request_error;
+pub mod protocol;
+pub mod headers;
+pub mod task_status;
+pub mod server_info;
+pub mod shutdown_report;
+pub mod poll_policy;
+pub mod retry_policy;
+pub mod session;
+pub mod pipeline;
+pub mod task_handle;
+pub mod batch;
+pub mod journal;
+
+/// Header names, written as the line prefix they appear with on the wire.
+pub mod prefix {
+    pub const SIZE: &str = "size: ";
+    pub const THREADS: &str = "number-of-threads: ";
+    pub const ID: &str = "id: ";
+    pub const ERROR: &str = "error: ";
+    pub const MESSAGE: &str = "message: ";
+    pub const TIME: &str = "execution-time: ";
+    pub const VERSION: &str = "protocol-version: ";
+    pub const FEATURES: &str = "features: ";
+    pub const PROGRESS: &str = "progress: ";
+    pub const QUEUE_POSITION: &str = "queue-position: ";
+    pub const TIMEOUT: &str = "timeout: ";
+    pub const STATUS: &str = "status: ";
+    pub const REQUEST_ID: &str = "request-id: ";
+    pub const RUNNING_TASKS: &str = "running-tasks: ";
+    pub const QUEUED_TASKS: &str = "queued-tasks: ";
+    pub const MODE: &str = "mode: ";
+    pub const GRACE_PERIOD: &str = "grace-period: ";
+    pub const DRAINED_TASKS: &str = "drained-tasks: ";
+    pub const ABORTED_TASKS: &str = "aborted-tasks: ";
+    pub const IDEMPOTENCY_KEY: &str = "idempotency-key: ";
+}
+
+pub use admin_client::AdminClient;
+pub use batch::{Batch, BatchItem, BatchResults};
+pub use client::{Client, TaskSpec};
+pub use config::shutdown_mode::ShutdownMode;
+pub use client_error::{ClientError, TimeoutKind};
+pub use client_config::{ClientConfig, ClientConfigBuilder, ConfigError};
+pub use journal::{Journal, JournalEntry};
+pub use lab1::matrix::Matrix;
+pub use poll_policy::{Backoff, PollPolicy};
+pub use request_error::RequestError;
+pub use retry_policy::RetryPolicy;
+pub use server_error::ServerError;
+pub use server_info::ServerInfo;
+pub use shutdown_report::ShutdownReport;
+pub use session::{ExecutionResult, Session, WaitOutcome};
+pub use task_handle::TaskHandle;
+pub use task_status::{Progress, TaskStatus};
@@ -1,28 +1,4 @@
-mod config {
-    pub mod request_type;
-    pub mod response_type;
-    pub mod status;
-}
-
-mod lab1 {
-    pub mod matrix;
-}
-
-mod client;
-mod print_writer;
-mod buffered_reader;
-mod custom_error;
-
-mod prefix {
-    pub const SIZE: &str = "size: ";
-    pub const THREADS: &str = "number-of-threads: ";
-    pub const ID: &str = "id: ";
-    // pub const ERROR: &str = "error: ";
-    pub const TIME: &str = "execution-time: ";
-}
-
-use client::Client;
-use std::thread;
+use parallel_lab4::{AdminClient, Client, ClientConfig, Journal, PollPolicy, RetryPolicy, ShutdownMode, TaskSpec};
 use std::time::Duration;
 use scoped_threadpool::Pool;
 
@@ -35,6 +11,9 @@ fn main() {
     const CPU_CORES: i32 = 8;
     const CPU_LOGICAL_CORES: i32 = 16;
     const MIN_THREADS: i32 = CPU_CORES / 2;
+    // Only servers that understand HELLO should be run with the handshake enabled.
+    const HANDSHAKE: bool = false;
+    const JOURNAL: &str = "tasks.journal";
     let thread_numbers: Vec<i32> = vec![
         CPU_LOGICAL_CORES * 16,
         // CPU_LOGICAL_CORES * 8,
@@ -55,7 +34,31 @@ fn main() {
         MIN_THREADS * 256,
     ];
 
-    thread::sleep(Duration::from_secs(1));
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let builder = ClientConfig::builder()
+        .address(HOST, PORT)
+        .handshake(HANDSHAKE)
+        // The server may still be starting up when the clients are launched.
+        .retry(RetryPolicy::exponential(10, Duration::from_millis(50), Duration::from_secs(2)))
+        .max_reconnects(3)
+        .journal(JOURNAL);
+    let builder = if args.is_empty() {
+        // Checks the status once and asks for the result even if it isn't ready, to test the server.
+        builder.poll_policy(PollPolicy::ask_before_ready())
+    } else {
+        builder
+    };
+    let config = match builder.build() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Invalid client configuration: {}", e);
+            return;
+        }
+    };
+    if !args.is_empty() {
+        run_command(&args, config);
+        return;
+    }
     /*
     Double Colon Operator (::):
 Used to access associated items of a module, such as constants, functions, or types (structs, enums, traits).
@@ -70,29 +73,66 @@ Example: std::vec::Vec::new(), String::from("hello").
         //The tasks are automatically joined when the scoped block ends, so there's no need to call join explicitly.
         for thread in thread_numbers {
             for size in &dimension_numbers {
-                let host = HOST.to_owned();
-                let port = PORT;
+                let config = config.clone();
                 let size = *size;
-                let thread = thread;
                 counter += 1;
                 let counter = counter;
                 scope.execute(move || {
-                    let mut client = Client::new(&host, port, size, counter, thread, -1);
-                    client.run();
+                    let mut client = Client::new(counter, config, TaskSpec { size, threads: thread });
+                    if let Err(e) = client.run() {
+                        eprintln!("Client {} failed: {}", counter, e);
+                    }
                 });
                 /*
                  The move keyword is used to move ownership of
-                  variables (host, port, size, thread, counter) into the closure (anonymous function: |...| { ... } syntax).
+                  variables (config, size, thread, counter) into the closure (anonymous function: |...| { ... } syntax).
                    This allows the closure to take ownership and access those variables.
                 */
             }
         }
     });
-    let mut killer = Client::new(HOST, PORT, -1, -1, -1, -1);
-    killer.run();
+    let admin = AdminClient::new(config);
+    if let Err(e) = admin.shutdown(ShutdownMode::Drain) {
+        eprintln!("Shutdown failed: {}", e);
+    }
 
     println!("Clients have finished work");
     /*
     Fundamentally, macros! are a way of writing code that writes other code, which is known as metaprogramming
     */
 }
+
+/// `list`, `submit <size> <threads>` and `fetch <task id>` work with the task journal,
+/// so a large matrix can be submitted now and its result collected by a later run.
+fn run_command(args: &[String], config: ClientConfig) {
+    let journal = match &config.journal {
+        Some(path) => Journal::new(path),
+        None => {
+            eprintln!("No journal is configured");
+            return;
+        }
+    };
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        ["list"] => match journal.pending() {
+            Ok(pending) if pending.is_empty() => println!("No pending tasks in {}", journal.path().display()),
+            Ok(pending) => pending.iter().for_each(|entry| println!("{}", entry)),
+            Err(e) => eprintln!("Failed to read {}: {}", journal.path().display(), e),
+        },
+        ["submit", size, threads] => match (size.parse(), threads.parse()) {
+            (Ok(size), Ok(threads)) => match Client::new(1, config, TaskSpec { size, threads }).submit() {
+                Ok(task_id) => println!("Submitted task {}", task_id),
+                Err(e) => eprintln!("Submit failed: {}", e),
+            },
+            _ => eprintln!("Size and number of threads must be integers"),
+        },
+        ["fetch", task_id] => match task_id.parse() {
+            Ok(task_id) => match Client::fetch(config, task_id) {
+                Ok(result) => println!("Result of task {} received. executionTime: {}", task_id, result.execution_time),
+                Err(e) => eprintln!("Fetch failed: {}", e),
+            },
+            Err(_) => eprintln!("Task id must be an integer"),
+        },
+        _ => eprintln!("Usage: parallel-lab4 [list | submit <size> <threads> | fetch <task id>]"),
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use crate::client_error::ClientError;
+use crate::config::feature::Feature;
+use crate::config::request_type::RequestType;
+use crate::protocol::{ProtocolViolation, Request, Response};
+use crate::session::{check_status, unexpected, Session};
+use crate::task_status::TaskStatus;
+
+/// Sends status requests back-to-back on one session and matches the replies, which may
+/// arrive in any order, to the requests by their `request-id: `.
+/// Needs a server that negotiated `pipelining` during `HELLO`.
+///
+/// Only statuses are pipelined. A result download is acknowledged with bare `OK` lines that
+/// carry no request id, so it must not overlap with anything else; finished tasks are
+/// collected with `Session::get_result` once the pipeline has been drained.
+#[derive(Debug)]
+pub struct Pipeline<'a> {
+    session: &'a mut Session,
+    /// Task id of every request that hasn't been answered yet, by request id.
+    outstanding: HashMap<u64, i64>,
+}
+
+/// The reply to one pipelined request. Rejections and failed tasks only affect their own reply;
+/// connection-level failures are returned by `Pipeline::receive` itself.
+#[derive(Debug)]
+pub struct Reply {
+    pub request_id: u64,
+    pub task_id: i64,
+    pub outcome: Result<TaskStatus, ClientError>,
+}
+
+impl<'a> Pipeline<'a> {
+    pub fn new(session: &'a mut Session) -> Result<Pipeline<'a>, ClientError> {
+        // Unlike other features this one can't be tried blindly: a server that doesn't echo
+        // request ids would leave `receive` waiting for a line that never comes.
+        if !session.supports(&Feature::Pipelining) {
+            return Err(ClientError::Unsupported(Feature::Pipelining));
+        }
+        Ok(Pipeline { session, outstanding: HashMap::new() })
+    }
+
+    /// Queues a `GET_TASK_STATUS`. With `pipelining` negotiated, a `DONE` reply is not
+    /// followed by the result.
+    pub fn status(&mut self, task_id: i64) -> Result<u64, ClientError> {
+        let request_id = self.session.send_tagged(&Request::GetTaskStatus { id: task_id })?;
+        self.outstanding.insert(request_id, task_id);
+        Ok(request_id)
+    }
+
+    pub fn outstanding(&self) -> usize {
+        self.outstanding.len()
+    }
+
+    /// Reads the next reply, whichever request it answers.
+    pub fn receive(&mut self) -> Result<Reply, ClientError> {
+        let request_id = self.session.receive_request_id()?;
+        let task_id = self.outstanding.remove(&request_id)
+            .ok_or_else(|| ProtocolViolation::new("the id of an outstanding request", &request_id.to_string()))?;
+        let outcome = match self.session.receive(&RequestType::GetTaskStatus)? {
+            Response::TaskStatus(task_status) => check_status(task_id, &task_status.status).map(|_| task_status),
+            Response::Rejected(error) => Err(error.into()),
+            other => return Err(unexpected("pipeline", other)),
+        };
+        Ok(Reply { request_id, task_id, outcome })
+    }
+
+    /// Reads replies until every queued request has been answered.
+    pub fn drain(&mut self) -> Result<Vec<Reply>, ClientError> {
+        let mut replies = Vec::with_capacity(self.outstanding());
+        while self.outstanding() > 0 {
+            replies.push(self.receive()?);
+        }
+        Ok(replies)
+    }
+}
+
+impl Session {
+    /// Checks the status of many tasks with one round trip instead of one per task.
+    pub fn poll_many(&mut self, task_ids: &[i64]) -> Result<Vec<Reply>, ClientError> {
+        let mut pipeline = Pipeline::new(self)?;
+        for task_id in task_ids {
+            pipeline.status(*task_id)?;
+        }
+        pipeline.drain()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufReader, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread::{self, JoinHandle};
+    use std::time::Duration;
+    use super::*;
+    use crate::client_config::ClientConfig;
+    use crate::config::status::Status;
+    use crate::protocol::Capabilities;
+
+    /// A server that negotiates `pipelining`, reads `count` status requests and lets `reply`
+    /// answer them all at once.
+    fn serve<F>(count: usize, reply: F) -> (Session, JoinHandle<()>)
+    where
+        F: FnOnce(&mut TcpStream, Vec<(i64, u64)>, &Capabilities) + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut input = BufReader::new(stream.try_clone().unwrap());
+            let capabilities = Capabilities { version: 2, features: vec![Feature::Pipelining] };
+            let (hello, _) = Request::decode(&mut input).unwrap();
+            assert_eq!(hello.request_type(), RequestType::Hello);
+            Response::Hello(capabilities.clone()).encode(&mut stream, &Capabilities::legacy()).unwrap();
+            let mut requests = Vec::new();
+            for _ in 0..count {
+                match Request::decode(&mut input).unwrap() {
+                    (Request::GetTaskStatus { id }, Some(request_id)) => requests.push((id, request_id)),
+                    other => panic!("unexpected request {:?}", other),
+                }
+            }
+            reply(&mut stream, requests, &capabilities);
+        });
+        let config = ClientConfig::builder()
+            .address("127.0.0.1", port)
+            .handshake(true)
+            .read_timeout(Duration::from_secs(5))
+            .logging(false)
+            .build()
+            .unwrap();
+        (Session::open(&config).unwrap(), server)
+    }
+
+    fn status_of(task_id: i64) -> Status {
+        if task_id % 2 == 0 { Status::DONE } else { Status::RUNNING }
+    }
+
+    #[test]
+    fn matches_replies_in_reverse_order_to_their_requests() {
+        let (mut session, server) = serve(3, |stream, requests, capabilities| {
+            for (task_id, request_id) in requests.into_iter().rev() {
+                Response::encode_request_id(stream, request_id).unwrap();
+                Response::TaskStatus(TaskStatus::new(status_of(task_id))).encode(stream, capabilities).unwrap();
+            }
+            stream.flush().unwrap();
+        });
+        let replies = session.poll_many(&[11, 12, 13]).unwrap();
+        server.join().unwrap();
+        let task_ids: Vec<i64> = replies.iter().map(|reply| reply.task_id).collect();
+        assert_eq!(task_ids, vec![13, 12, 11]);
+        for reply in replies {
+            assert_eq!(reply.outcome.unwrap().status, status_of(reply.task_id));
+        }
+    }
+
+    #[test]
+    fn unknown_request_id_is_a_protocol_violation() {
+        let (mut session, server) = serve(1, |stream, requests, capabilities| {
+            Response::encode_request_id(stream, requests[0].1 + 100).unwrap();
+            Response::TaskStatus(TaskStatus::new(Status::RUNNING)).encode(stream, capabilities).unwrap();
+        });
+        let error = session.poll_many(&[11]).unwrap_err();
+        server.join().unwrap();
+        assert!(error.is_protocol_violation(), "{}", error);
+    }
+
+    #[test]
+    fn requires_pipelining() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let config = ClientConfig::builder().address("127.0.0.1", port).logging(false).build().unwrap();
+        let mut legacy = Session::connect(&config).unwrap();
+        assert!(matches!(Pipeline::new(&mut legacy), Err(ClientError::Unsupported(Feature::Pipelining))));
+    }
+}
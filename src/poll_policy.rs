@@ -0,0 +1,140 @@
+use std::time::Duration;
+
+/// How `Client::await_result` polls `GET_TASK_STATUS` until the task is `DONE`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PollPolicy {
+    pub backoff: Backoff,
+    /// Gives up after this many status requests.
+    pub max_attempts: Option<u32>,
+    /// Gives up once this much time has passed since polling started.
+    pub deadline: Option<Duration>,
+    /// Test mode: instead of giving up, request the result anyway so the server's handling
+    /// of a premature `GET_RESULT` can be exercised.
+    pub ask_before_ready: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Backoff {
+    Fixed(Duration),
+    /// The delay doubles after every attempt until it reaches `max`.
+    Exponential { initial: Duration, max: Duration },
+}
+
+impl PollPolicy {
+    pub fn fixed(interval: Duration) -> PollPolicy {
+        PollPolicy {
+            backoff: Backoff::Fixed(interval),
+            max_attempts: None,
+            deadline: None,
+            ask_before_ready: false,
+        }
+    }
+
+    pub fn exponential(initial: Duration, max: Duration) -> PollPolicy {
+        PollPolicy {
+            backoff: Backoff::Exponential { initial, max },
+            ..PollPolicy::fixed(initial)
+        }
+    }
+
+    /// The original benchmark behaviour: check the status once and then ask for the result
+    /// whether it is ready or not.
+    pub fn ask_before_ready() -> PollPolicy {
+        PollPolicy {
+            max_attempts: Some(1),
+            ask_before_ready: true,
+            ..PollPolicy::fixed(Duration::from_millis(1))
+        }
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> PollPolicy {
+        self.max_attempts = Some(max_attempts);
+        self
+    }
+
+    pub fn with_deadline(mut self, deadline: Duration) -> PollPolicy {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Delay before the next status request, `attempt` being the number of requests made so far.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        match self.backoff {
+            Backoff::Fixed(interval) => interval,
+            Backoff::Exponential { initial, max } => {
+                let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+                initial.saturating_mul(factor).min(max)
+            }
+        }
+    }
+
+    pub fn is_exhausted(&self, attempts: u32, elapsed: Duration) -> bool {
+        self.max_attempts.is_some_and(|max| attempts >= max)
+            || self.deadline.is_some_and(|deadline| elapsed >= deadline)
+    }
+
+    /// Time left until the deadline, if there is one.
+    pub fn remaining(&self, elapsed: Duration) -> Option<Duration> {
+        self.deadline.map(|deadline| deadline.saturating_sub(elapsed))
+    }
+}
+
+impl Default for PollPolicy {
+    fn default() -> Self {
+        PollPolicy::exponential(Duration::from_millis(1), Duration::from_millis(500))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exponential_delay_doubles_up_to_the_cap() {
+        let policy = PollPolicy::exponential(Duration::from_millis(10), Duration::from_millis(50));
+        let delays: Vec<u64> = (0..6).map(|attempt| policy.delay(attempt).as_millis() as u64).collect();
+        assert_eq!(delays, vec![10, 10, 20, 40, 50, 50]);
+    }
+
+    #[test]
+    fn exponential_delay_saturates_at_large_attempt_counts() {
+        let policy = PollPolicy::exponential(Duration::from_millis(10), Duration::from_secs(60));
+        assert_eq!(policy.delay(u32::MAX), Duration::from_secs(60));
+        let uncapped = PollPolicy::exponential(Duration::MAX / 2, Duration::MAX);
+        assert_eq!(uncapped.delay(u32::MAX), Duration::MAX);
+    }
+
+    #[test]
+    fn fixed_delay_never_changes() {
+        let policy = PollPolicy::fixed(Duration::from_millis(7));
+        assert_eq!(policy.delay(1), Duration::from_millis(7));
+        assert_eq!(policy.delay(1000), Duration::from_millis(7));
+    }
+
+    #[test]
+    fn exhausted_by_attempts() {
+        let policy = PollPolicy::fixed(Duration::from_millis(1)).with_max_attempts(3);
+        assert!(!policy.is_exhausted(2, Duration::from_secs(3600)));
+        assert!(policy.is_exhausted(3, Duration::ZERO));
+    }
+
+    #[test]
+    fn exhausted_by_deadline() {
+        let policy = PollPolicy::fixed(Duration::from_millis(1)).with_deadline(Duration::from_secs(2));
+        assert!(!policy.is_exhausted(u32::MAX, Duration::from_millis(1999)));
+        assert!(policy.is_exhausted(1, Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn unlimited_policy_is_never_exhausted() {
+        assert!(!PollPolicy::default().is_exhausted(u32::MAX, Duration::MAX));
+    }
+
+    #[test]
+    fn remaining_time_stops_at_zero() {
+        let policy = PollPolicy::fixed(Duration::from_millis(1)).with_deadline(Duration::from_secs(2));
+        assert_eq!(policy.remaining(Duration::from_millis(500)), Some(Duration::from_millis(1500)));
+        assert_eq!(policy.remaining(Duration::from_secs(5)), Some(Duration::ZERO));
+        assert_eq!(PollPolicy::default().remaining(Duration::from_secs(5)), None);
+    }
+}
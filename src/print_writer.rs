@@ -1,15 +1,18 @@
 use std::io::{self, Write};
-use crate::config::request_type::RequestType;
 use crate::config::response_type::ResponseType;
+use crate::protocol::Request;
 use byteorder::{WriteBytesExt, BigEndian};
 
+#[derive(Debug)]
 pub(crate) struct Writer<W: Write> {
     pub out: W,
+    /// Print matrix transfer progress and timings.
+    pub logging: bool,
 }
 
 impl<W: Write> Writer<W> {
     pub(crate) fn new(out: W) -> Self {
-        Writer { out }
+        Writer { out, logging: true }
     }
 
     pub fn println(&mut self, text: &str) -> io::Result<()> {
@@ -19,31 +22,38 @@ impl<W: Write> Writer<W> {
         Ok(())
     }
 
-    pub fn println_request(&mut self, request: RequestType) {
-        self.println(&request.to_string()).unwrap();
+    pub fn send(&mut self, request: &Request) -> io::Result<()> {
+        request.encode(&mut self.out, None)
     }
 
-    pub fn println_end(&mut self) {
-        self.println("").unwrap()
+    pub fn send_tagged(&mut self, request: &Request, request_id: u64) -> io::Result<()> {
+        request.encode(&mut self.out, Some(request_id))
     }
 
-    pub fn println_response(&mut self, response: ResponseType) {
-        self.println(&response.to_string()).unwrap()
+    pub fn println_response(&mut self, response: ResponseType) -> io::Result<()> {
+        self.println(&response.to_string())
     }
 
     pub fn write_matrix(&mut self, data: &[Vec<f64>], client: String) -> io::Result<()> {
         let start = std::time::Instant::now();
         let size = data.len();
-        for i in 0..size {
-            for j in 0..size {
-                self.out.write_f64::<BigEndian>(data[i][j])?;
+        // One write per row rather than per value, which with `TCP_NODELAY` would be one packet each.
+        let mut buffer = Vec::with_capacity(size * 8);
+        for (i, row) in data.iter().enumerate() {
+            buffer.clear();
+            for value in row {
+                buffer.write_f64::<BigEndian>(*value)?;
             }
-            if size >= 2000 && i % 1000 == 0 {
+            self.out.write_all(&buffer)?;
+            if self.logging && size >= 2000 && i % 1000 == 0 {
                 println!("Writing matrix of the size: {}, row: {}", size, i);
             }
         }
+        self.out.flush()?;
         let finish = start.elapsed().as_micros();
-        println!("Time to write: {} {}", finish, client);
+        if self.logging {
+            println!("Time to write: {} {}", finish, client);
+        }
         Ok(())
     }
 }
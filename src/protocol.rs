@@ -0,0 +1,670 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+use crate::config::request_type::RequestType;
+use crate::config::response_type::ResponseType;
+use crate::config::error_code::ErrorCode;
+use crate::config::feature::Feature;
+use crate::config::shutdown_mode::ShutdownMode;
+use crate::config::status::Status;
+use crate::headers::{HeaderError, Headers};
+use crate::prefix::{ABORTED_TASKS, DRAINED_TASKS, ERROR, FEATURES, GRACE_PERIOD, ID, IDEMPOTENCY_KEY, MESSAGE, MODE, PROGRESS, QUEUE_POSITION, QUEUED_TASKS, RUNNING_TASKS, SIZE, REQUEST_ID, STATUS, THREADS, TIME, TIMEOUT, VERSION};
+use crate::server_error::ServerError;
+use crate::server_info::ServerInfo;
+use crate::shutdown_report::ShutdownReport;
+use crate::task_status::TaskStatus;
+
+/// A request as it travels over the wire: the verb line followed by a header block.
+#[derive(Debug, PartialEq)]
+pub enum Request {
+    /// A server that already accepted a task with the same `idempotency_key` answers with
+    /// that task's id instead of creating a new one.
+    PostNewTask { threads: i32, size: i32, idempotency_key: Option<String> },
+    StartTask { id: i64 },
+    GetTaskStatus { id: i64 },
+    GetResult { id: i64 },
+    /// `grace_period` is in milliseconds. A request without `mode: ` comes from a client that
+    /// predates shutdown modes and is treated as `now`.
+    Shutdown { mode: ShutdownMode, grace_period: Option<u64> },
+    Hello { version: u32, features: Vec<Feature> },
+    CancelTask { id: i64 },
+    /// `timeout` is in milliseconds.
+    WaitForResult { id: i64, timeout: u64 },
+    Ping,
+    ServerInfo,
+}
+
+/// Protocol version this client speaks. Servers that do not understand `HELLO` are treated as version 1.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// A random key for `idempotency-key: `, unique enough that two clients never pick the same one.
+pub fn new_idempotency_key() -> String {
+    format!("{:032x}", rand::random::<u128>())
+}
+
+/// What both sides of a connection agreed on during the `HELLO` exchange.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Capabilities {
+    pub version: u32,
+    pub features: Vec<Feature>,
+}
+
+/// A reply from the server. Which variant is expected depends on the request that was sent,
+/// so decoding always takes the `RequestType` being answered.
+#[derive(Debug, PartialEq)]
+pub enum Response {
+    /// Bare `OK`, e.g. the answer to `START_TASK`, `CANCEL_TASK` or to the `POST_NEW_TASK` headers.
+    Accepted,
+    /// `BAD_REQUEST` followed by an `error: ` header block.
+    Rejected(ServerError),
+    /// `id: ` line the server sends once the posted matrix has been received; a header block
+    /// from version 2 on.
+    TaskPosted { id: i64 },
+    /// `OK` followed by the status line of the task and, with `extended-status`, a header block.
+    TaskStatus(TaskStatus),
+    /// `OK` followed by an `execution-time: ` line (a header block from version 2 on), after which
+    /// the result matrix is sent.
+    ResultReady { execution_time: i64 },
+    /// `OK` followed by `drained-tasks: `, `aborted-tasks: ` and `message: ` headers, or,
+    /// from servers that predate shutdown modes, a single free-form line.
+    Shutdown(ShutdownReport),
+    /// `OK` followed by the server's `protocol-version: ` and `features: ` headers.
+    Hello(Capabilities),
+    /// Answer to `WAIT_FOR_RESULT` when the timeout elapsed first: `OK` and a header block
+    /// with `status: ` and, if known, `progress: ` and `queue-position: `.
+    /// A finished task is answered with `ResultReady` instead.
+    TimedOut(TaskStatus),
+    /// `OK` followed by the `protocol-version: `, `features: ` and task counter headers.
+    ServerInfo(ServerInfo),
+}
+
+/// The server sent a line that makes no sense at this point of the conversation,
+/// e.g. leftover matrix bytes. Nothing read from the connection afterwards can be trusted.
+#[derive(Debug)]
+pub struct ProtocolViolation {
+    pub expected: String,
+    pub line: String,
+}
+
+impl Request {
+    pub fn request_type(&self) -> RequestType {
+        match self {
+            Request::PostNewTask { .. } => RequestType::PostNewTask,
+            Request::StartTask { .. } => RequestType::StartTask,
+            Request::GetTaskStatus { .. } => RequestType::GetTaskStatus,
+            Request::GetResult { .. } => RequestType::GetResult,
+            Request::Shutdown { .. } => RequestType::SHUTDOWN,
+            Request::Hello { .. } => RequestType::Hello,
+            Request::CancelTask { .. } => RequestType::CancelTask,
+            Request::WaitForResult { .. } => RequestType::WaitForResult,
+            Request::Ping => RequestType::Ping,
+            Request::ServerInfo => RequestType::ServerInfo,
+        }
+    }
+
+    pub fn headers(&self) -> Headers {
+        let mut headers = Headers::new();
+        match self {
+            Request::PostNewTask { threads, size, idempotency_key } => {
+                headers.insert(THREADS, threads).insert(SIZE, size);
+                if let Some(key) = idempotency_key {
+                    headers.insert(IDEMPOTENCY_KEY, key);
+                }
+            }
+            Request::StartTask { id }
+            | Request::GetTaskStatus { id }
+            | Request::GetResult { id }
+            | Request::CancelTask { id } => {
+                headers.insert(ID, id);
+            }
+            Request::Shutdown { mode, grace_period } => {
+                headers.insert(MODE, mode);
+                if let Some(grace_period) = grace_period {
+                    headers.insert(GRACE_PERIOD, grace_period);
+                }
+            }
+            Request::Ping | Request::ServerInfo => {}
+            Request::WaitForResult { id, timeout } => {
+                headers.insert(ID, id).insert(TIMEOUT, timeout);
+            }
+            Request::Hello { version, features } => {
+                headers.insert(VERSION, version).insert(FEATURES, join_features(features));
+            }
+        }
+        headers
+    }
+
+    /// Writes the request. A `request_id` is sent as a `request-id: ` header, which a server that
+    /// negotiated `request-ids` or `pipelining` echoes in front of its reply.
+    pub fn encode<W: Write>(&self, out: &mut W, request_id: Option<u64>) -> io::Result<()> {
+        writeln!(out, "{}", self.request_type())?;
+        let mut headers = self.headers();
+        if let Some(request_id) = request_id {
+            headers.insert(REQUEST_ID, request_id);
+        }
+        headers.write(out)?;
+        out.flush()
+    }
+
+    pub fn decode<R: BufRead>(input: &mut R) -> io::Result<(Request, Option<u64>)> {
+        let verb = read_line(input)?;
+        let request_type = RequestType::from_str(&verb)
+            .map_err(|_| ProtocolViolation::new("a request verb", &verb))?;
+        let headers = Headers::read(input)?;
+        let request = match request_type {
+            RequestType::PostNewTask => Request::PostNewTask {
+                threads: headers.require(THREADS)?,
+                size: headers.require(SIZE)?,
+                idempotency_key: headers.optional(IDEMPOTENCY_KEY)?,
+            },
+            RequestType::StartTask => Request::StartTask { id: headers.require(ID)? },
+            RequestType::GetTaskStatus => Request::GetTaskStatus { id: headers.require(ID)? },
+            RequestType::GetResult => Request::GetResult { id: headers.require(ID)? },
+            RequestType::CancelTask => Request::CancelTask { id: headers.require(ID)? },
+            RequestType::WaitForResult => Request::WaitForResult {
+                id: headers.require(ID)?,
+                timeout: headers.require(TIMEOUT)?,
+            },
+            RequestType::SHUTDOWN => Request::Shutdown {
+                mode: headers.optional(MODE)?.unwrap_or(ShutdownMode::Now),
+                grace_period: headers.optional(GRACE_PERIOD)?,
+            },
+            RequestType::Ping => Request::Ping,
+            RequestType::ServerInfo => Request::ServerInfo,
+            RequestType::Hello => Request::Hello {
+                version: headers.require(VERSION)?,
+                features: split_features(headers.get(FEATURES)),
+            },
+        };
+        Ok((request, headers.optional(REQUEST_ID)?))
+    }
+}
+
+impl Response {
+    pub fn encode<W: Write>(&self, out: &mut W, capabilities: &Capabilities) -> io::Result<()> {
+        match self {
+            Response::Accepted => writeln!(out, "{}", ResponseType::OK)?,
+            Response::Rejected(error) => {
+                writeln!(out, "{}", ResponseType::BadRequest)?;
+                if capabilities.header_blocks() {
+                    Headers::new().insert(ERROR, &error.code).insert(MESSAGE, &error.message).write(out)?;
+                } else {
+                    writeln!(out, "{}{}", ERROR, format!("{} {}", error.code, error.message).trim())?;
+                }
+            }
+            Response::TaskPosted { id } => {
+                write_reply_headers(Headers::new().insert(ID, id), out, capabilities)?;
+            }
+            Response::TaskStatus(task_status) => {
+                writeln!(out, "{}", ResponseType::OK)?;
+                writeln!(out, "{}", task_status.status)?;
+                if capabilities.supports(&Feature::ExtendedStatus) {
+                    progress_headers(task_status).write(out)?;
+                }
+            }
+            Response::TimedOut(task_status) => {
+                writeln!(out, "{}", ResponseType::OK)?;
+                progress_headers(task_status).insert(STATUS, &task_status.status).write(out)?;
+            }
+            Response::ResultReady { execution_time } => {
+                writeln!(out, "{}", ResponseType::OK)?;
+                write_reply_headers(Headers::new().insert(TIME, execution_time), out, capabilities)?;
+            }
+            Response::Shutdown(report) => {
+                if report.drained_tasks.is_none() && report.aborted_tasks.is_none() {
+                    writeln!(out, "{}", report.message)?;
+                } else {
+                    writeln!(out, "{}", ResponseType::OK)?;
+                    let mut headers = Headers::new();
+                    if let Some(drained) = report.drained_tasks {
+                        headers.insert(DRAINED_TASKS, drained);
+                    }
+                    if let Some(aborted) = report.aborted_tasks {
+                        headers.insert(ABORTED_TASKS, aborted);
+                    }
+                    headers.insert(MESSAGE, &report.message).write(out)?;
+                }
+            }
+            Response::Hello(capabilities) => {
+                writeln!(out, "{}", ResponseType::OK)?;
+                Headers::new()
+                    .insert(VERSION, capabilities.version)
+                    .insert(FEATURES, join_features(&capabilities.features))
+                    .write(out)?;
+            }
+            Response::ServerInfo(info) => {
+                writeln!(out, "{}", ResponseType::OK)?;
+                let mut headers = Headers::new();
+                headers.insert(VERSION, info.version).insert(FEATURES, join_features(&info.features));
+                if let Some(running) = info.running_tasks {
+                    headers.insert(RUNNING_TASKS, running);
+                }
+                if let Some(queued) = info.queued_tasks {
+                    headers.insert(QUEUED_TASKS, queued);
+                }
+                headers.write(out)?;
+            }
+        }
+        out.flush()
+    }
+
+    /// Reads the reply to a request of the given type, in the dialect negotiated by `HELLO`.
+    /// The `id: ` block that follows an uploaded matrix is read with `decode_task_id` instead.
+    pub fn decode<R: BufRead>(input: &mut R, request: &RequestType, capabilities: &Capabilities) -> io::Result<Response> {
+        let line = read_line(input)?;
+        let response_type = match ResponseType::from_str(&line) {
+            Ok(response_type) => response_type,
+            Err(_) if *request == RequestType::SHUTDOWN => {
+                return Ok(Response::Shutdown(ShutdownReport::legacy(&line)));
+            }
+            Err(_) => return Err(ProtocolViolation::new("OK or BAD_REQUEST", &line).into()),
+        };
+        if response_type == ResponseType::BadRequest {
+            return Ok(Response::Rejected(read_server_error(input, capabilities)?));
+        }
+        match request {
+            RequestType::GetTaskStatus => {
+                let line = read_line(input)?;
+                let status = Status::from_str(&line)
+                    .map_err(|_| ProtocolViolation::new("a task status", &line))?;
+                let mut task_status = TaskStatus::new(status);
+                if capabilities.supports(&Feature::ExtendedStatus) {
+                    read_progress(&Headers::read(input)?, &mut task_status)?;
+                }
+                Ok(Response::TaskStatus(task_status))
+            }
+            RequestType::GetResult => Ok(Response::ResultReady {
+                execution_time: read_reply_headers(input, capabilities)?.require(TIME)?,
+            }),
+            RequestType::WaitForResult => {
+                let headers = Headers::read(input)?;
+                if let Some(execution_time) = headers.optional(TIME)? {
+                    return Ok(Response::ResultReady { execution_time });
+                }
+                let line: String = headers.require(STATUS)?;
+                let status = Status::from_str(&line)
+                    .map_err(|_| ProtocolViolation::new("a task status", &line))?;
+                let mut task_status = TaskStatus::new(status);
+                read_progress(&headers, &mut task_status)?;
+                Ok(Response::TimedOut(task_status))
+            }
+            RequestType::Hello => {
+                let headers = Headers::read(input)?;
+                Ok(Response::Hello(Capabilities {
+                    version: headers.require(VERSION)?,
+                    features: split_features(headers.get(FEATURES)),
+                }))
+            }
+            RequestType::SHUTDOWN => {
+                let headers = Headers::read(input)?;
+                Ok(Response::Shutdown(ShutdownReport {
+                    message: headers.get(MESSAGE).unwrap_or_default().to_string(),
+                    drained_tasks: headers.optional(DRAINED_TASKS)?,
+                    aborted_tasks: headers.optional(ABORTED_TASKS)?,
+                }))
+            }
+            RequestType::ServerInfo => {
+                let headers = Headers::read(input)?;
+                Ok(Response::ServerInfo(ServerInfo {
+                    version: headers.require(VERSION)?,
+                    features: split_features(headers.get(FEATURES)),
+                    running_tasks: headers.optional(RUNNING_TASKS)?,
+                    queued_tasks: headers.optional(QUEUED_TASKS)?,
+                }))
+            }
+            _ => Ok(Response::Accepted),
+        }
+    }
+
+    pub fn decode_task_id<R: BufRead>(input: &mut R, capabilities: &Capabilities) -> io::Result<Response> {
+        Ok(Response::TaskPosted { id: read_reply_headers(input, capabilities)?.require(ID)? })
+    }
+
+    /// Writes the `request-id: ` line a pipelined reply starts with.
+    pub fn encode_request_id<W: Write>(out: &mut W, request_id: u64) -> io::Result<()> {
+        writeln!(out, "{}{}", REQUEST_ID, request_id)
+    }
+
+    /// Reads the `request-id: ` line a pipelined reply starts with; the reply itself is then
+    /// decoded with `decode` for whatever request that id was given to.
+    pub fn decode_request_id<R: BufRead>(input: &mut R) -> io::Result<u64> {
+        let line = read_line(input)?;
+        let mut headers = Headers::new();
+        headers.push_line(&line).map_err(|_| ProtocolViolation::new("a request-id line", &line))?;
+        Ok(headers.require(REQUEST_ID)?)
+    }
+}
+
+impl Capabilities {
+    /// What a server that answers `HELLO` with `BAD_REQUEST` is assumed to support.
+    pub fn legacy() -> Capabilities {
+        Capabilities { version: 1, features: Vec::new() }
+    }
+
+    /// Everything this client knows how to use. `compression` is left out until the client
+    /// can actually compress and decompress matrices.
+    pub fn client() -> Capabilities {
+        Capabilities {
+            version: PROTOCOL_VERSION,
+            features: vec![
+                Feature::Cancellation,
+                Feature::ExtendedStatus,
+                Feature::LongPoll,
+                Feature::Pipelining,
+                Feature::RequestIds,
+                Feature::Idempotency,
+            ],
+        }
+    }
+
+    /// Keeps the lower of the two versions and only the features both sides advertised.
+    pub fn negotiate(&self, server: &Capabilities) -> Capabilities {
+        Capabilities {
+            version: self.version.min(server.version),
+            features: self.features.iter()
+                .filter(|feature| server.supports(feature))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    pub fn supports(&self, feature: &Feature) -> bool {
+        self.features.contains(feature)
+    }
+
+    /// Whether the `id: `, `execution-time: ` and `error: ` replies are header blocks ended by a blank line.
+    /// Version 1 servers send just the one line, and the next reply follows right after it.
+    pub fn header_blocks(&self) -> bool {
+        self.version >= 2
+    }
+}
+
+fn progress_headers(task_status: &TaskStatus) -> Headers {
+    let mut headers = Headers::new();
+    if let Some(progress) = task_status.progress {
+        headers.insert(PROGRESS, progress);
+    }
+    if let Some(position) = task_status.queue_position {
+        headers.insert(QUEUE_POSITION, position);
+    }
+    headers
+}
+
+fn read_progress(headers: &Headers, task_status: &mut TaskStatus) -> Result<(), HeaderError> {
+    task_status.progress = headers.optional(PROGRESS)?;
+    task_status.queue_position = headers.optional(QUEUE_POSITION)?;
+    Ok(())
+}
+
+/// Reads the headers of a reply that version 1 servers send as a single unterminated line.
+fn read_reply_headers<R: BufRead>(input: &mut R, capabilities: &Capabilities) -> io::Result<Headers> {
+    if capabilities.header_blocks() {
+        return Headers::read(input);
+    }
+    let mut headers = Headers::new();
+    headers.push_line(&read_line(input)?)?;
+    Ok(headers)
+}
+
+fn write_reply_headers<W: Write>(headers: &Headers, out: &mut W, capabilities: &Capabilities) -> io::Result<()> {
+    if capabilities.header_blocks() {
+        return headers.write(out);
+    }
+    headers.write_lines(out)
+}
+
+fn join_features(features: &[Feature]) -> String {
+    features.iter().map(|feature| feature.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+fn split_features(value: Option<&str>) -> Vec<Feature> {
+    value.unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .filter_map(|name| name.parse().ok())
+        .collect()
+}
+
+/// Reads what follows `BAD_REQUEST`. Servers that predate error codes send a single free-form line,
+/// which is kept as the message of an `ErrorCode::Other`. Version 1 servers send a single
+/// `error: <code> <message>` line; from version 2 on `error: ` starts a header block.
+fn read_server_error<R: BufRead>(input: &mut R, capabilities: &Capabilities) -> io::Result<ServerError> {
+    let line = read_line(input)?;
+    let text = match line.strip_prefix(ERROR.trim_end()) {
+        Some(text) => text.trim(),
+        None => return Ok(ServerError::new(ErrorCode::Other(String::new()), &line)),
+    };
+    if !capabilities.header_blocks() {
+        let (code, message) = text.split_once(' ').unwrap_or((text, ""));
+        return Ok(match code.parse() {
+            Ok(ErrorCode::Other(_)) | Err(_) => ServerError::new(ErrorCode::Other(String::new()), text),
+            Ok(code) => ServerError::new(code, message.trim()),
+        });
+    }
+    let mut headers = Headers::new();
+    headers.push_line(&line)?;
+    headers.read_into(input)?;
+    let code: ErrorCode = headers.require(ERROR)?;
+    let message = headers.get(MESSAGE).unwrap_or_default();
+    Ok(ServerError::new(code, message))
+}
+
+fn read_line<R: BufRead>(input: &mut R) -> io::Result<String> {
+    let mut line = String::new();
+    if input.read_line(&mut line)? == 0 {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "No data read from the stream."));
+    }
+    Ok(line.trim().to_string())
+}
+
+impl ProtocolViolation {
+    pub fn new(expected: &str, line: &str) -> ProtocolViolation {
+        ProtocolViolation {
+            expected: expected.to_string(),
+            line: line.to_string(),
+        }
+    }
+
+    /// Whether the error, however it was wrapped on the way up, means the stream is out of sync.
+    pub fn is_cause_of(error: &(dyn Error + 'static)) -> bool {
+        let mut current = Some(error);
+        while let Some(error) = current {
+            let io_inner = error.downcast_ref::<io::Error>().and_then(|e| e.get_ref());
+            if error.is::<ProtocolViolation>() || io_inner.is_some_and(|inner| inner.is::<ProtocolViolation>()) {
+                return true;
+            }
+            current = error.source();
+        }
+        false
+    }
+}
+
+impl fmt::Display for ProtocolViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Protocol violation: expected {}, got '{}'", self.expected, self.line)
+    }
+}
+
+impl Error for ProtocolViolation {}
+
+impl From<ProtocolViolation> for io::Error {
+    fn from(error: ProtocolViolation) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use super::*;
+    use crate::task_status::Progress;
+
+    fn round_trip_request(request: Request, request_id: Option<u64>) {
+        let mut out = Cursor::new(Vec::new());
+        request.encode(&mut out, request_id).unwrap();
+        out.set_position(0);
+        assert_eq!(Request::decode(&mut out).unwrap(), (request, request_id));
+    }
+
+    fn round_trip_response(response: Response, request: RequestType, capabilities: &Capabilities) {
+        let mut out = Cursor::new(Vec::new());
+        response.encode(&mut out, capabilities).unwrap();
+        out.set_position(0);
+        assert_eq!(Response::decode(&mut out, &request, capabilities).unwrap(), response);
+    }
+
+    fn decode_response(wire: &str, request: RequestType) -> io::Result<Response> {
+        Response::decode(&mut Cursor::new(wire.as_bytes()), &request, &Capabilities::legacy())
+    }
+
+    fn running() -> TaskStatus {
+        TaskStatus {
+            status: Status::RUNNING,
+            progress: Some(Progress::Rows { done: 256, total: 1024 }),
+            queue_position: None,
+        }
+    }
+
+    #[test]
+    fn requests_round_trip() {
+        let requests = vec![
+            Request::PostNewTask { threads: 4, size: 1000, idempotency_key: None },
+            Request::PostNewTask { threads: 4, size: 1000, idempotency_key: Some(new_idempotency_key()) },
+            Request::StartTask { id: 7 },
+            Request::GetTaskStatus { id: 7 },
+            Request::GetResult { id: 7 },
+            Request::Shutdown { mode: ShutdownMode::Now, grace_period: None },
+            Request::Shutdown { mode: ShutdownMode::Drain, grace_period: Some(30_000) },
+            Request::Hello { version: PROTOCOL_VERSION, features: Capabilities::client().features },
+            Request::CancelTask { id: 7 },
+            Request::WaitForResult { id: 7, timeout: 5_000 },
+            Request::Ping,
+            Request::ServerInfo,
+        ];
+        for request in requests {
+            round_trip_request(request, None);
+        }
+        round_trip_request(Request::GetTaskStatus { id: 7 }, Some(42));
+    }
+
+    #[test]
+    fn legacy_shutdown_request_means_now() {
+        let mut input = Cursor::new(b"SHUTDOWN\n\n".to_vec());
+        let (request, request_id) = Request::decode(&mut input).unwrap();
+        assert_eq!(request, Request::Shutdown { mode: ShutdownMode::Now, grace_period: None });
+        assert_eq!(request_id, None);
+    }
+
+    #[test]
+    fn unknown_verb_is_a_protocol_violation() {
+        let error = Request::decode(&mut Cursor::new(b"FLY_AWAY\n\n".to_vec())).unwrap_err();
+        assert!(ProtocolViolation::is_cause_of(&error));
+    }
+
+    #[test]
+    fn responses_round_trip() {
+        let legacy = Capabilities::legacy();
+        let extended = Capabilities { version: PROTOCOL_VERSION, features: vec![Feature::ExtendedStatus] };
+        let rejected = ServerError::new(ErrorCode::UnknownTask, "no task with id 7");
+        round_trip_response(Response::Accepted, RequestType::StartTask, &legacy);
+        round_trip_response(Response::Rejected(rejected.clone()), RequestType::GetResult, &legacy);
+        round_trip_response(Response::Rejected(rejected), RequestType::GetResult, &extended);
+        round_trip_response(Response::TaskStatus(TaskStatus::new(Status::WAITING)), RequestType::GetTaskStatus, &legacy);
+        round_trip_response(Response::TaskStatus(running()), RequestType::GetTaskStatus, &extended);
+        round_trip_response(Response::ResultReady { execution_time: 1234 }, RequestType::GetResult, &legacy);
+        round_trip_response(Response::ResultReady { execution_time: 1234 }, RequestType::GetResult, &extended);
+        round_trip_response(Response::ResultReady { execution_time: 1234 }, RequestType::WaitForResult, &extended);
+        round_trip_response(Response::TimedOut(running()), RequestType::WaitForResult, &extended);
+        round_trip_response(Response::Hello(Capabilities::client()), RequestType::Hello, &legacy);
+        let report = ShutdownReport { message: String::from("Bye"), drained_tasks: Some(3), aborted_tasks: Some(1) };
+        round_trip_response(Response::Shutdown(report), RequestType::SHUTDOWN, &legacy);
+        let info = ServerInfo {
+            version: PROTOCOL_VERSION,
+            features: vec![Feature::LongPoll, Feature::Other(String::from("teleport"))],
+            running_tasks: Some(2),
+            queued_tasks: None,
+        };
+        round_trip_response(Response::ServerInfo(info), RequestType::ServerInfo, &legacy);
+    }
+
+    #[test]
+    fn task_id_round_trips() {
+        let mut out = Cursor::new(Vec::new());
+        Response::TaskPosted { id: 99 }.encode(&mut out, &Capabilities::client()).unwrap();
+        out.set_position(0);
+        assert_eq!(Response::decode_task_id(&mut out, &Capabilities::client()).unwrap(), Response::TaskPosted { id: 99 });
+    }
+
+    #[test]
+    fn legacy_task_id_is_a_single_line() {
+        let mut input = Cursor::new(b"id: 7\nOK\n".to_vec());
+        let legacy = Capabilities::legacy();
+        assert_eq!(Response::decode_task_id(&mut input, &legacy).unwrap(), Response::TaskPosted { id: 7 });
+        assert_eq!(Response::decode(&mut input, &RequestType::StartTask, &legacy).unwrap(), Response::Accepted);
+    }
+
+    #[test]
+    fn legacy_execution_time_is_a_single_line() {
+        let mut input = Cursor::new(b"OK\nexecution-time: 1234\n\x40\x00".to_vec());
+        let response = Response::decode(&mut input, &RequestType::GetResult, &Capabilities::legacy()).unwrap();
+        assert_eq!(response, Response::ResultReady { execution_time: 1234 });
+        assert_eq!(input.position(), 24);
+    }
+
+    #[test]
+    fn request_id_round_trips() {
+        let mut out = Cursor::new(Vec::new());
+        Response::encode_request_id(&mut out, 42).unwrap();
+        out.set_position(0);
+        assert_eq!(Response::decode_request_id(&mut out).unwrap(), 42);
+    }
+
+    #[test]
+    fn legacy_bad_request_keeps_the_line_as_message() {
+        let response = decode_response("BAD_REQUEST\nMatrix is too big\n", RequestType::PostNewTask).unwrap();
+        let error = ServerError::new(ErrorCode::Other(String::new()), "Matrix is too big");
+        assert_eq!(response, Response::Rejected(error));
+    }
+
+    #[test]
+    fn legacy_error_line_is_read_on_its_own() {
+        let response = decode_response("BAD_REQUEST\nerror: SIZE_TOO_LARGE\n", RequestType::PostNewTask).unwrap();
+        assert_eq!(response, Response::Rejected(ServerError::new(ErrorCode::SizeTooLarge, "")));
+        let response = decode_response("BAD_REQUEST\nerror: UNKNOWN_TASK no task 7\n", RequestType::GetResult).unwrap();
+        assert_eq!(response, Response::Rejected(ServerError::new(ErrorCode::UnknownTask, "no task 7")));
+        let response = decode_response("BAD_REQUEST\nerror: Matrix is too big\n", RequestType::PostNewTask).unwrap();
+        let error = ServerError::new(ErrorCode::Other(String::new()), "Matrix is too big");
+        assert_eq!(response, Response::Rejected(error));
+    }
+
+    #[test]
+    fn legacy_shutdown_reply_is_a_free_form_line() {
+        let report = ShutdownReport::legacy("Server is shutting down");
+        round_trip_response(Response::Shutdown(report.clone()), RequestType::SHUTDOWN, &Capabilities::legacy());
+        let response = decode_response("Server is shutting down\n", RequestType::SHUTDOWN).unwrap();
+        assert_eq!(response, Response::Shutdown(report));
+    }
+
+    #[test]
+    fn unexpected_reply_is_a_protocol_violation() {
+        let error = decode_response("0.0\n", RequestType::GetTaskStatus).unwrap_err();
+        assert!(ProtocolViolation::is_cause_of(&error));
+        let error = decode_response("OK\n\n", RequestType::GetTaskStatus).unwrap_err();
+        assert!(ProtocolViolation::is_cause_of(&error));
+    }
+
+    #[test]
+    fn unknown_status_is_kept_verbatim() {
+        let response = decode_response("OK\nSLEEPING\n", RequestType::GetTaskStatus).unwrap();
+        assert_eq!(response, Response::TaskStatus(TaskStatus::new(Status::Unknown(String::from("SLEEPING")))));
+    }
+
+    #[test]
+    fn negotiation_keeps_common_features_and_lower_version() {
+        let server = Capabilities { version: 1, features: vec![Feature::LongPoll, Feature::Compression] };
+        let agreed = Capabilities::client().negotiate(&server);
+        assert_eq!(agreed, Capabilities { version: 1, features: vec![Feature::LongPoll] });
+    }
+}
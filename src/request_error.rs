@@ -0,0 +1,41 @@
+use std::error::Error;
+use std::fmt;
+use crate::client_error::ClientError;
+use crate::config::request_type::RequestType;
+use crate::session::Session;
+
+/// An error together with the request that was in flight when it happened,
+/// so it can be matched with the server's log by `request-id`.
+#[derive(Debug)]
+pub struct RequestError {
+    pub request_id: u64,
+    pub request_type: RequestType,
+    pub source: Box<ClientError>,
+}
+
+impl RequestError {
+    /// Attaches the session's current request to `source`, if a request has been sent
+    /// and `source` doesn't carry one already.
+    pub fn wrap(session: &Session, source: ClientError) -> ClientError {
+        match (session.current_request(), &source) {
+            (_, ClientError::Request(_)) | (None, _) => source,
+            (Some((request_id, request_type)), _) => ClientError::Request(RequestError {
+                request_id: *request_id,
+                request_type: request_type.clone(),
+                source: Box::new(source),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} request-id: {}. {}", self.request_type, self.request_id, self.source)
+    }
+}
+
+impl Error for RequestError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
@@ -0,0 +1,109 @@
+use std::time::Duration;
+use rand::Rng;
+
+/// How `Session::connect` retries a connection the server refused or didn't accept in time,
+/// e.g. because it is still starting up. `Client` also repeats a lost idempotent post this way.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Connection attempts in total, including the first one.
+    pub attempts: u32,
+    /// Delay after the first failed attempt; it doubles after every further one.
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Fraction of each delay, between 0 and 1, that is randomized so clients started together
+    /// don't retry in lockstep.
+    pub jitter: f64,
+}
+
+impl RetryPolicy {
+    /// A single attempt.
+    pub fn none() -> RetryPolicy {
+        RetryPolicy {
+            attempts: 1,
+            base_delay: Duration::ZERO,
+            max_delay: Duration::ZERO,
+            jitter: 0.0,
+        }
+    }
+
+    pub fn exponential(attempts: u32, base_delay: Duration, max_delay: Duration) -> RetryPolicy {
+        RetryPolicy {
+            attempts,
+            base_delay,
+            max_delay,
+            jitter: 0.5,
+        }
+    }
+
+    pub fn with_jitter(mut self, jitter: f64) -> RetryPolicy {
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.attempts > 1
+    }
+
+    /// Delay before the next attempt, `attempt` being the number of attempts made so far.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        let delay = self.base_delay.saturating_mul(factor).min(self.max_delay);
+        // The fields are public, so the policy may not have been through `ClientConfig::build`.
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        if jitter.is_nan() || jitter == 0.0 {
+            return delay;
+        }
+        let randomized = rand::thread_rng().gen_range(0.0..=jitter);
+        delay.mul_f64(1.0 - randomized)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy::none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(jitter: f64) -> RetryPolicy {
+        RetryPolicy::exponential(10, Duration::from_millis(100), Duration::from_secs(1)).with_jitter(jitter)
+    }
+
+    #[test]
+    fn delay_doubles_up_to_the_cap() {
+        let delays: Vec<u64> = (1..7).map(|attempt| policy(0.0).delay(attempt).as_millis() as u64).collect();
+        assert_eq!(delays, vec![100, 200, 400, 800, 1000, 1000]);
+    }
+
+    #[test]
+    fn delay_saturates_at_large_attempt_counts() {
+        assert_eq!(policy(0.0).delay(u32::MAX), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn jitter_stays_within_its_fraction() {
+        for attempt in 1..50 {
+            let full = policy(0.0).delay(attempt);
+            let delay = policy(0.25).delay(attempt);
+            assert!(delay <= full && delay >= full.mul_f64(0.75), "{:?} out of bounds for {:?}", delay, full);
+        }
+    }
+
+    #[test]
+    fn out_of_range_jitter_is_clamped() {
+        for _ in 0..50 {
+            assert!(policy(7.0).delay(3) <= Duration::from_millis(400));
+            assert_eq!(policy(-1.0).delay(3), Duration::from_millis(400));
+            assert_eq!(policy(f64::NAN).delay(3), Duration::from_millis(400));
+        }
+    }
+
+    #[test]
+    fn enabled_with_more_than_one_attempt() {
+        assert!(!RetryPolicy::none().is_enabled());
+        assert!(policy(0.0).is_enabled());
+    }
+}
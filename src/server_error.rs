@@ -0,0 +1,28 @@
+use std::error::Error;
+use std::fmt;
+use crate::config::error_code::ErrorCode;
+
+/// The reason the server gave for answering `BAD_REQUEST`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerError {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl ServerError {
+    pub fn new(code: ErrorCode, message: &str) -> ServerError {
+        ServerError {
+            code,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ServerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Server error {}: {}", self.code, self.message)
+    }
+}
+
+impl Error for ServerError {
+}
@@ -0,0 +1,24 @@
+use std::fmt;
+use crate::config::feature::Feature;
+
+/// The answer to `SERVER_INFO`. The task counters are only sent by servers that track them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerInfo {
+    pub version: u32,
+    pub features: Vec<Feature>,
+    pub running_tasks: Option<u32>,
+    pub queued_tasks: Option<u32>,
+}
+
+impl fmt::Display for ServerInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ServerInfo {{ protocolVersion={}, features={:?}", self.version, self.features)?;
+        if let Some(running) = self.running_tasks {
+            write!(f, ", runningTasks={}", running)?;
+        }
+        if let Some(queued) = self.queued_tasks {
+            write!(f, ", queuedTasks={}", queued)?;
+        }
+        write!(f, " }}")
+    }
+}
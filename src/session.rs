@@ -0,0 +1,591 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufReader};
+use std::net::{Shutdown, SocketAddr, TcpStream, ToSocketAddrs};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::buffered_reader::BufferedReader;
+use crate::client_error::{ClientError, TimeoutKind};
+use crate::client_config::ClientConfig;
+use crate::config::feature::Feature;
+use crate::config::request_type::RequestType;
+use crate::config::response_type::ResponseType::OK;
+use crate::config::shutdown_mode::ShutdownMode;
+use crate::config::status::Status;
+use crate::lab1::matrix::Matrix;
+use crate::poll_policy::PollPolicy;
+use crate::print_writer::Writer;
+use crate::protocol::{Capabilities, ProtocolViolation, Request, Response};
+use crate::request_error::RequestError;
+use crate::server_info::ServerInfo;
+use crate::shutdown_report::ShutdownReport;
+use crate::task_status::TaskStatus;
+use socket2::{Domain, Protocol, SockAddr, Socket, TcpKeepalive, Type};
+
+/// How much longer than the requested long-poll timeout `wait` waits for the server's reply.
+const LONG_POLL_MARGIN: Duration = Duration::from_secs(5);
+
+/// One connection to the server over which any number of tasks can be posted, started,
+/// polled and fetched one after another.
+#[derive(Debug)]
+pub struct Session {
+    server: String,
+    reader: BufferedReader<TcpStream>,
+    writer: Writer<TcpStream>,
+    handshake: bool,
+    capabilities: Capabilities,
+    /// When each task started on this session, for ETA estimates.
+    started: HashMap<i64, Instant>,
+    next_request_id: u64,
+    /// The most recent request, for logs and errors.
+    current_request: Option<(u64, RequestType)>,
+    /// Whether requests carry their `request-id: ` on the wire and replies echo it.
+    tag_requests: bool,
+    /// Task whose result was being downloaded when the connection failed, so a resumed
+    /// session knows to fetch it again with `GET_RESULT`.
+    interrupted_download: Option<i64>,
+    config: ClientConfig,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExecutionResult {
+    pub matrix: Matrix,
+    pub execution_time: i64,
+}
+
+/// A status check either finds the task finished, in which case the result is downloaded
+/// right away, or still pending.
+#[derive(Debug)]
+pub enum WaitOutcome {
+    Done(ExecutionResult),
+    Pending(TaskStatus),
+}
+
+impl fmt::Display for Session {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Session {{ server={}, protocolVersion={}", self.server, self.capabilities.version)?;
+        if let Some((request_id, request_type)) = &self.current_request {
+            write!(f, ", request={}, requestId={}", request_type, request_id)?;
+        }
+        write!(f, " }}")
+    }
+}
+
+impl Session {
+    /// Connects and, if `config` asks for it, performs the `HELLO` handshake. This is how every
+    /// client in the crate opens its sessions.
+    pub fn open(config: &ClientConfig) -> Result<Session, ClientError> {
+        let mut session = Session::connect(config)?;
+        if config.handshake {
+            let result = session.hello().map(|_| ());
+            session.conclude(result)?;
+        }
+        Ok(session)
+    }
+
+    /// Opens a connection with the socket options from `config`, retrying according to its
+    /// retry policy. The handshake is left to the caller; see `open`.
+    pub fn connect(config: &ClientConfig) -> Result<Session, ClientError> {
+        let server = config.server();
+        let mut attempts: u32 = 0;
+        let stream = loop {
+            attempts += 1;
+            let source = match open_stream(config) {
+                Ok(stream) => break stream,
+                Err(source) => source,
+            };
+            if attempts >= config.retry.attempts {
+                return Err(match source.kind() {
+                    io::ErrorKind::TimedOut => ClientError::Timeout {
+                        kind: TimeoutKind::Connect,
+                        message: format!("{} didn't accept the connection within {:?}", server, config.connect_timeout.unwrap_or_default()),
+                    },
+                    _ => ClientError::Connect { server, source },
+                });
+            }
+            let delay = config.retry.delay(attempts);
+            if config.logging {
+                println!("Failed to connect to {} (attempt {} of {}): {}. Retrying in {:?}", server, attempts, config.retry.attempts, source, delay);
+            }
+            thread::sleep(delay);
+        };
+        let mut reader = BufferedReader::new(BufReader::new(stream.try_clone()?));
+        reader.logging = config.logging;
+        let mut writer = Writer::new(stream);
+        writer.logging = config.logging;
+        Ok(Session {
+            server,
+            reader,
+            writer,
+            handshake: false,
+            capabilities: Capabilities::legacy(),
+            started: HashMap::new(),
+            next_request_id: 1,
+            current_request: None,
+            tag_requests: false,
+            interrupted_download: None,
+            config: config.clone(),
+        })
+    }
+
+    /// Replaces a broken connection with a new one to the same server, repeating the handshake
+    /// if there was one. Started tasks and the request counter carry over, so polling can go on
+    /// by task id. On failure the session is left as it was.
+    pub fn reconnect(&mut self) -> Result<(), ClientError> {
+        let mut fresh = Session::connect(&self.config)?;
+        fresh.next_request_id = self.next_request_id;
+        if self.handshake {
+            fresh.hello()?;
+        } else {
+            fresh.tag_requests = self.tag_requests;
+        }
+        self.abort();
+        fresh.started = std::mem::take(&mut self.started);
+        fresh.interrupted_download = self.interrupted_download;
+        fresh.log(format_args!("Reconnected. {}", fresh));
+        *self = fresh;
+        Ok(())
+    }
+
+    /// The task whose result download was cut off by a connection failure, if any.
+    pub fn interrupted_download(&self) -> Option<i64> {
+        self.interrupted_download
+    }
+
+    /// Sends `request-id: ` with every request and expects it echoed in front of every reply.
+    /// Turned on by `hello` when the server advertises `request-ids`; only force it for servers
+    /// known to echo the header.
+    pub fn set_request_ids(&mut self, enabled: bool) {
+        self.tag_requests = enabled;
+    }
+
+    /// The id and type of the request sent last.
+    pub fn current_request(&self) -> Option<&(u64, RequestType)> {
+        self.current_request.as_ref()
+    }
+
+    /// Attaches the request in flight to an error of `result`. If the error means the stream is
+    /// out of sync, the connection is aborted, so nothing else gets read from it.
+    pub fn conclude<T>(&mut self, result: Result<T, ClientError>) -> Result<T, ClientError> {
+        let result = result.map_err(|source| RequestError::wrap(self, source));
+        if let Err(e) = &result {
+            if e.is_protocol_violation() {
+                eprintln!("Aborting connection, the stream is out of sync. {} {}", self, e);
+                self.abort();
+            }
+        }
+        result
+    }
+
+    /// Closes the connection without waiting for anything the server still has to say.
+    pub fn abort(&mut self) {
+        let _ = self.writer.out.shutdown(Shutdown::Both);
+    }
+
+    /// Exchanges `HELLO`. A server that rejects it is assumed to speak protocol version 1.
+    pub fn hello(&mut self) -> Result<&Capabilities, ClientError> {
+        let ours = Capabilities::client();
+        let request = Request::Hello { version: ours.version, features: ours.features.clone() };
+        self.capabilities = match self.exchange(&request)? {
+            Response::Hello(server) => ours.negotiate(&server),
+            Response::Rejected(error) => {
+                self.log(format_args!("Server doesn't support HELLO, falling back to protocol version 1. {}", error));
+                Capabilities::legacy()
+            }
+            other => return Err(unexpected("hello()", other)),
+        };
+        self.handshake = true;
+        self.reader.capabilities = self.capabilities.clone();
+        self.tag_requests = self.supports(&Feature::RequestIds) || self.supports(&Feature::Pipelining);
+        self.log(format_args!("Protocol version: {}, features: {:?}. {}", self.capabilities.version, self.capabilities.features, self));
+        Ok(&self.capabilities)
+    }
+
+    pub fn post_task(&mut self, matrix: &Matrix, threads: i32) -> Result<i64, ClientError> {
+        self.post_task_with_key(matrix, threads, None)
+    }
+
+    /// Posting again with the same key, e.g. after the connection failed before the task id
+    /// was read, returns the id of the task the server already accepted. A key needs a
+    /// server that negotiated `idempotency`.
+    pub fn post_task_with_key(&mut self, matrix: &Matrix, threads: i32, idempotency_key: Option<&str>) -> Result<i64, ClientError> {
+        if matrix.size == 0 {
+            return Err(ClientError::InvalidTask(String::from("Cannot post an empty matrix")));
+        }
+        if matrix.data.len() != matrix.size || matrix.data.iter().any(|row| row.len() != matrix.size) {
+            let message = format!("Cannot post a matrix that is not {0}x{0}", matrix.size);
+            return Err(ClientError::InvalidTask(message));
+        }
+        if idempotency_key.is_some() {
+            self.require_feature(&Feature::Idempotency)?;
+        }
+        let request = Request::PostNewTask {
+            threads,
+            size: matrix.size as i32,
+            idempotency_key: idempotency_key.map(String::from),
+        };
+        match self.exchange(&request)? {
+            Response::Accepted => {
+                self.writer.write_matrix(&matrix.data, self.to_string()).map_err(writing)?;
+                match self.reader.receive_task_id().map_err(reading)? {
+                    Response::TaskPosted { id } => Ok(id),
+                    other => Err(unexpected("postTask()", other)),
+                }
+            }
+            Response::Rejected(error) => Err(error.into()),
+            other => Err(unexpected("postTask()", other)),
+        }
+    }
+
+    pub fn start_task(&mut self, task_id: i64) -> Result<(), ClientError> {
+        match self.exchange(&Request::StartTask { id: task_id })? {
+            Response::Accepted => {
+                self.started.insert(task_id, Instant::now());
+                Ok(())
+            }
+            Response::Rejected(error) => Err(error.into()),
+            other => Err(unexpected("startTask()", other)),
+        }
+    }
+
+    /// Asks for the status once and downloads the result if the task is `DONE`.
+    pub fn poll(&mut self, task_id: i64, size: usize) -> Result<WaitOutcome, ClientError> {
+        let task_status = self.request_status(task_id)?;
+        if task_status.status == Status::DONE {
+            let result = if self.result_follows_done() {
+                self.read_result(task_id, size)
+            } else {
+                self.get_result(task_id, size)
+            };
+            return result.map(WaitOutcome::Done);
+        }
+        Ok(WaitOutcome::Pending(task_status))
+    }
+
+    /// Asks for the status once without downloading anything. Needs a server that negotiated
+    /// `pipelining`: any other server follows `DONE` with the result, which would have to be read.
+    pub fn status(&mut self, task_id: i64) -> Result<TaskStatus, ClientError> {
+        if self.result_follows_done() {
+            return Err(ClientError::Unsupported(Feature::Pipelining));
+        }
+        self.request_status(task_id)
+    }
+
+    /// Whether the server offers the result right after a `DONE` status. Servers that negotiated
+    /// `pipelining` don't, since the offer would have to be acknowledged with untagged lines.
+    pub(crate) fn result_follows_done(&self) -> bool {
+        !self.supports(&Feature::Pipelining)
+    }
+
+    fn request_status(&mut self, task_id: i64) -> Result<TaskStatus, ClientError> {
+        let request = Request::GetTaskStatus { id: task_id };
+        let task_status = match self.exchange(&request) {
+            Ok(Response::TaskStatus(task_status)) => task_status,
+            Ok(Response::Rejected(error)) => return Err(error.into()),
+            Ok(other) => return Err(unexpected("getStatus()", other)),
+            Err(e) => {
+                eprintln!("task: {}. IOException in getStatus(). {}", task_id, self);
+                return Err(e);
+            }
+        };
+        check_status(task_id, &task_status.status)?;
+        Ok(task_status)
+    }
+
+    /// Sends `GET_RESULT` whether or not the task is done; the server decides.
+    pub fn get_result(&mut self, task_id: i64, size: usize) -> Result<ExecutionResult, ClientError> {
+        let request_id = self.send(&Request::GetResult { id: task_id })?;
+        self.expect_echo(request_id)?;
+        self.read_result(task_id, size)
+    }
+
+    /// Blocks until the task is done or `timeout` elapses, using one `WAIT_FOR_RESULT` request
+    /// instead of repeated polling.
+    pub fn wait(&mut self, task_id: i64, size: usize, timeout: Duration) -> Result<WaitOutcome, ClientError> {
+        self.require_feature(&Feature::LongPoll)?;
+        let request = Request::WaitForResult { id: task_id, timeout: timeout.as_millis() as u64 };
+        // The server holds the reply for up to `timeout`, so the socket has to wait a bit longer.
+        let read_timeout = self.config.read_timeout;
+        let long_poll = read_timeout.map(|read_timeout| read_timeout.max(timeout.saturating_add(LONG_POLL_MARGIN)));
+        if long_poll != read_timeout {
+            self.writer.out.set_read_timeout(long_poll)?;
+        }
+        let response = self.exchange(&request);
+        if long_poll != read_timeout {
+            self.writer.out.set_read_timeout(read_timeout)?;
+        }
+        match response? {
+            Response::ResultReady { execution_time } => {
+                self.download_result(task_id, size, execution_time).map(WaitOutcome::Done)
+            }
+            Response::TimedOut(task_status) => {
+                check_status(task_id, &task_status.status)?;
+                Ok(WaitOutcome::Pending(task_status))
+            }
+            Response::Rejected(error) => Err(error.into()),
+            other => Err(unexpected("waitForResult()", other)),
+        }
+    }
+
+    /// Polls according to `policy` and downloads the result once the task is `DONE`.
+    pub fn await_result(&mut self, task_id: i64, size: usize, policy: &PollPolicy) -> Result<ExecutionResult, ClientError> {
+        let mut attempts: u32 = 0;
+        self.await_result_since(task_id, size, policy, Instant::now(), &mut attempts)
+    }
+
+    /// `await_result` for polling that began at `start` and has made `attempts` status requests
+    /// so far, e.g. on an earlier session. `attempts` is kept up to date, so the policy's budget
+    /// can be carried over to the next session if this one fails.
+    pub(crate) fn await_result_since(
+        &mut self,
+        task_id: i64,
+        size: usize,
+        policy: &PollPolicy,
+        start: Instant,
+        attempts: &mut u32,
+    ) -> Result<ExecutionResult, ClientError> {
+        let started = *self.started.get(&task_id).unwrap_or(&start);
+        loop {
+            let task_status = match self.poll(task_id, size)? {
+                WaitOutcome::Done(result) => return Ok(result),
+                WaitOutcome::Pending(task_status) => task_status,
+            };
+            *attempts += 1;
+            match task_status.estimate_remaining(started.elapsed()) {
+                Some(eta) => self.log(format_args!("Result is not ready yet. task: {}, status: {}, ETA: {:?}. {}", task_id, task_status, eta, self)),
+                None => self.log(format_args!("Result is not ready yet. task: {}, status: {}. {}", task_id, task_status, self)),
+            }
+            if policy.is_exhausted(*attempts, start.elapsed()) {
+                break;
+            }
+            let delay = policy.delay(*attempts);
+            thread::sleep(policy.remaining(start.elapsed()).map_or(delay, |remaining| delay.min(remaining)));
+        }
+        if policy.ask_before_ready {
+            self.log(format_args!("The result is not ready, but the client asks for it. task: {}. {}", task_id, self));
+            return self.get_result(task_id, size);
+        }
+        let message = format!("task: {}. Gave up waiting after {} attempts in {:?}", task_id, attempts, start.elapsed());
+        Err(ClientError::Timeout { kind: TimeoutKind::Poll, message })
+    }
+
+    pub fn cancel_task(&mut self, task_id: i64) -> Result<(), ClientError> {
+        self.require_feature(&Feature::Cancellation)?;
+        match self.exchange(&Request::CancelTask { id: task_id })? {
+            Response::Accepted => {
+                self.started.remove(&task_id);
+                self.log(format_args!("Task {} cancelled. {}", task_id, self));
+                Ok(())
+            }
+            Response::Rejected(error) => Err(error.into()),
+            other => Err(unexpected("cancelTask()", other)),
+        }
+    }
+
+    /// With `ShutdownMode::Drain`, the server waits up to `grace_period` for running tasks
+    /// before aborting the rest; without a grace period it waits for all of them.
+    pub fn shutdown_server(&mut self, mode: ShutdownMode, grace_period: Option<Duration>) -> Result<ShutdownReport, ClientError> {
+        let grace_period = grace_period.map(|grace_period| grace_period.as_millis() as u64);
+        match self.exchange(&Request::Shutdown { mode, grace_period })? {
+            Response::Shutdown(report) => Ok(report),
+            Response::Rejected(error) => Err(error.into()),
+            other => Err(unexpected("shutdownServer()", other)),
+        }
+    }
+
+    /// Returns the round-trip time of a `PING`.
+    pub fn ping(&mut self) -> Result<Duration, ClientError> {
+        let start = Instant::now();
+        match self.exchange(&Request::Ping)? {
+            Response::Accepted => Ok(start.elapsed()),
+            Response::Rejected(error) => Err(error.into()),
+            other => Err(unexpected("ping()", other)),
+        }
+    }
+
+    pub fn server_info(&mut self) -> Result<ServerInfo, ClientError> {
+        match self.exchange(&Request::ServerInfo)? {
+            Response::ServerInfo(info) => Ok(info),
+            Response::Rejected(error) => Err(error.into()),
+            other => Err(unexpected("serverInfo()", other)),
+        }
+    }
+
+    pub fn supports(&self, feature: &Feature) -> bool {
+        self.capabilities.supports(feature)
+    }
+
+    /// Sends a request carrying a fresh `request-id: ` without waiting for the reply.
+    pub(crate) fn send_tagged(&mut self, request: &Request) -> Result<u64, ClientError> {
+        let request_id = self.begin(request);
+        self.writer.send_tagged(request, request_id).map_err(writing)?;
+        Ok(request_id)
+    }
+
+    /// Sends a request; its id only goes on the wire when request ids are enabled.
+    fn send(&mut self, request: &Request) -> Result<u64, ClientError> {
+        let request_id = self.begin(request);
+        if self.tag_requests {
+            self.writer.send_tagged(request, request_id).map_err(writing)?;
+        } else {
+            self.writer.send(request).map_err(writing)?;
+        }
+        Ok(request_id)
+    }
+
+    fn begin(&mut self, request: &Request) -> u64 {
+        let request_id = self.next_request_id;
+        self.next_request_id += 1;
+        self.current_request = Some((request_id, request.request_type()));
+        request_id
+    }
+
+    /// Reads the echoed `request-id: ` line when request ids are enabled.
+    fn expect_echo(&mut self, request_id: u64) -> Result<(), ClientError> {
+        if !self.tag_requests {
+            return Ok(());
+        }
+        let echoed = self.receive_request_id()?;
+        if echoed != request_id {
+            let expected = format!("request-id {}", request_id);
+            return Err(ProtocolViolation::new(&expected, &echoed.to_string()).into());
+        }
+        Ok(())
+    }
+
+    pub(crate) fn receive_request_id(&mut self) -> Result<u64, ClientError> {
+        self.reader.receive_request_id().map_err(reading)
+    }
+
+    pub(crate) fn receive(&mut self, request_type: &RequestType) -> Result<Response, ClientError> {
+        self.reader.receive(request_type).map_err(reading)
+    }
+
+    fn exchange(&mut self, request: &Request) -> Result<Response, ClientError> {
+        let request_id = self.send(request)?;
+        self.expect_echo(request_id)?;
+        self.receive(&request.request_type())
+    }
+
+    pub(crate) fn read_result(&mut self, task_id: i64, size: usize) -> Result<ExecutionResult, ClientError> {
+        match self.reader.receive(&RequestType::GetResult).map_err(reading)? {
+            Response::ResultReady { execution_time } => self.download_result(task_id, size, execution_time),
+            Response::Rejected(error) => Err(error.into()),
+            other => Err(unexpected("readResult()", other)),
+        }
+    }
+
+    pub(crate) fn download_result(&mut self, task_id: i64, size: usize, execution_time: i64) -> Result<ExecutionResult, ClientError> {
+        self.log(format_args!("Downloading the result: task: {} executionTime: {}. {}", task_id, execution_time, self));
+        self.interrupted_download = Some(task_id);
+        self.writer.println_response(OK).map_err(writing)?;
+        let label = format!("task: {}", task_id);
+        let read: Vec<Vec<f64>> = self.reader.read_matrix(size, &label).map_err(reading)?;
+        /*
+        In Rust, 'usize' is an unsigned integer type that represents the size of memory in bytes.
+         It is platform-dependent, meaning its size depends on the architecture of the underlying system.
+        The usize type is commonly used for indexing and representing the size of collections,
+         arrays, and memory allocations. It is guaranteed to be able to hold the size of the largest possible object
+         that can be created on the current platform.
+        */
+        self.writer.println_response(OK).map_err(writing)?;
+        self.interrupted_download = None;
+        self.started.remove(&task_id);
+        Ok(
+            ExecutionResult {
+                matrix: Matrix {
+                    size,
+                    data: read,
+                },
+                execution_time,
+            }
+        )
+    }
+
+    /// Prints `message` unless logging is turned off in the client configuration.
+    pub(crate) fn log(&self, message: fmt::Arguments) {
+        if self.config.logging {
+            println!("{}", message);
+        }
+    }
+
+    /// Refuses to use a feature the server did not advertise. Without a handshake the server's
+    /// features are unknown, so the request is sent and the server gets to reject it.
+    fn require_feature(&self, feature: &Feature) -> Result<(), ClientError> {
+        if self.handshake && !self.capabilities.supports(feature) {
+            return Err(ClientError::Unsupported(feature.clone()));
+        }
+        Ok(())
+    }
+}
+
+/// Connects to the first address `config.host` resolves to that accepts the connection.
+fn open_stream(config: &ClientConfig) -> io::Result<TcpStream> {
+    let mut last_error = None;
+    for address in (config.host.as_str(), config.port).to_socket_addrs()? {
+        match open_socket(&address, config) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, format!("{} did not resolve to any address", config.host))
+    }))
+}
+
+/// Buffer sizes are set before connecting so they can affect the TCP window negotiated
+/// in the handshake; everything else once the connection is up.
+fn open_socket(address: &SocketAddr, config: &ClientConfig) -> io::Result<TcpStream> {
+    let socket = Socket::new(Domain::for_address(*address), Type::STREAM, Some(Protocol::TCP))?;
+    if let Some(size) = config.send_buffer_size {
+        socket.set_send_buffer_size(size)?;
+    }
+    if let Some(size) = config.recv_buffer_size {
+        socket.set_recv_buffer_size(size)?;
+    }
+    let address = SockAddr::from(*address);
+    match config.connect_timeout {
+        Some(timeout) => socket.connect_timeout(&address, timeout)?,
+        None => socket.connect(&address)?,
+    }
+    socket.set_nodelay(config.nodelay)?;
+    if let Some(idle) = config.keepalive {
+        socket.set_tcp_keepalive(&TcpKeepalive::new().with_time(idle))?;
+    }
+    socket.set_read_timeout(config.read_timeout)?;
+    socket.set_write_timeout(config.write_timeout)?;
+    Ok(socket.into())
+}
+
+fn reading(error: io::Error) -> ClientError {
+    ClientError::from_io(error, TimeoutKind::Read)
+}
+
+fn writing(error: io::Error) -> ClientError {
+    ClientError::from_io(error, TimeoutKind::Write)
+}
+
+/// Turns the statuses a task can never leave without reaching `DONE` into errors.
+pub(crate) fn check_status(task_id: i64, status: &Status) -> Result<(), ClientError> {
+    match status {
+        Status::WAITING | Status::RUNNING | Status::DONE => Ok(()),
+        Status::CANCELLED => Err(ClientError::Cancelled { task_id }),
+        Status::EXPIRED => Err(task_error(task_id, "Task result has expired on the server")),
+        Status::FAILED(reason) => Err(task_error(task_id, &format!("Task failed: {}", reason))),
+        Status::Unknown(status) => {
+            // A newer server may report intermediate states we don't know about yet; keep polling.
+            eprintln!("Unknown task status '{}', treating it as not ready. task: {}", status, task_id);
+            Ok(())
+        }
+    }
+}
+
+fn task_error(task_id: i64, reason: &str) -> ClientError {
+    ClientError::TaskFailed { task_id, reason: reason.to_string() }
+}
+
+/// A well-formed reply that doesn't answer the request that was sent, so the replies
+/// are no longer lined up with the requests.
+pub(crate) fn unexpected(call: &str, response: Response) -> ClientError {
+    ProtocolViolation::new(&format!("a reply to {}", call), &format!("{:?}", response)).into()
+}
@@ -0,0 +1,35 @@
+use std::fmt;
+
+/// What the server did with its tasks when it acknowledged `SHUTDOWN`. Servers that predate
+/// shutdown modes answer with a single free-form line, so only `message` is set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShutdownReport {
+    pub message: String,
+    /// Tasks that were allowed to finish.
+    pub drained_tasks: Option<u32>,
+    /// Tasks that were still running or queued when the server stopped.
+    pub aborted_tasks: Option<u32>,
+}
+
+impl ShutdownReport {
+    pub fn legacy(message: &str) -> ShutdownReport {
+        ShutdownReport {
+            message: message.to_string(),
+            drained_tasks: None,
+            aborted_tasks: None,
+        }
+    }
+}
+
+impl fmt::Display for ShutdownReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ShutdownReport {{ message='{}'", self.message)?;
+        if let Some(drained) = self.drained_tasks {
+            write!(f, ", drainedTasks={}", drained)?;
+        }
+        if let Some(aborted) = self.aborted_tasks {
+            write!(f, ", abortedTasks={}", aborted)?;
+        }
+        write!(f, " }}")
+    }
+}
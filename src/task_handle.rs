@@ -0,0 +1,101 @@
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+use crate::client_error::ClientError;
+use crate::config::status::Status;
+use crate::poll_policy::PollPolicy;
+use crate::session::{ExecutionResult, Session, WaitOutcome};
+use crate::task_status::TaskStatus;
+
+/// A task posted to the server and started, as returned by `Client::post_task`. The handle shares
+/// its session with other handles, so an application can keep many tasks in flight and collect
+/// them in any order.
+#[derive(Debug)]
+pub struct TaskHandle {
+    id: i64,
+    size: usize,
+    session: Arc<Mutex<Session>>,
+    /// Unless the server negotiated `pipelining`, a `DONE` status is followed by the result on the
+    /// wire, so a status check can end up downloading the result before anyone asked for it.
+    /// It is kept here until then.
+    finished: Option<ExecutionResult>,
+}
+
+impl fmt::Display for TaskHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TaskHandle {{ id={}, size={} }}", self.id, self.size)
+    }
+}
+
+impl TaskHandle {
+    pub fn new(id: i64, size: usize, session: Arc<Mutex<Session>>) -> TaskHandle {
+        TaskHandle { id, size, session, finished: None }
+    }
+
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn status(&mut self) -> Result<TaskStatus, ClientError> {
+        if self.finished.is_some() {
+            return Ok(TaskStatus::new(Status::DONE));
+        }
+        let outcome = self.request(|session| {
+            if session.result_follows_done() {
+                session.poll(self.id, self.size)
+            } else {
+                // Even a `DONE` status: the result stays on the server until it is asked for.
+                session.status(self.id).map(WaitOutcome::Pending)
+            }
+        })?;
+        match outcome {
+            WaitOutcome::Done(result) => {
+                self.finished = Some(result);
+                Ok(TaskStatus::new(Status::DONE))
+            }
+            WaitOutcome::Pending(task_status) => Ok(task_status),
+        }
+    }
+
+    /// Returns the result if the task is done, without waiting.
+    pub fn try_result(&mut self) -> Result<Option<ExecutionResult>, ClientError> {
+        if let Some(result) = self.finished.take() {
+            return Ok(Some(result));
+        }
+        match self.poll()? {
+            WaitOutcome::Done(result) => Ok(Some(result)),
+            WaitOutcome::Pending(_) => Ok(None),
+        }
+    }
+
+    /// Polls according to `policy` until the result is available.
+    pub fn wait(&mut self, policy: &PollPolicy) -> Result<ExecutionResult, ClientError> {
+        if let Some(result) = self.finished.take() {
+            return Ok(result);
+        }
+        self.request(|session| session.await_result(self.id, self.size, policy))
+    }
+
+    pub fn cancel(&self) -> Result<(), ClientError> {
+        self.request(|session| session.cancel_task(self.id))
+    }
+
+    fn poll(&self) -> Result<WaitOutcome, ClientError> {
+        self.request(|session| session.poll(self.id, self.size))
+    }
+
+    /// Runs `action` on the shared session. If the stream got out of sync, the session is
+    /// aborted, so the other handles fail fast instead of reading garbage.
+    fn request<T>(&self, action: impl FnOnce(&mut Session) -> Result<T, ClientError>) -> Result<T, ClientError> {
+        let mut session = self.lock()?;
+        let result = action(&mut session);
+        session.conclude(result)
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, Session>, ClientError> {
+        self.session.lock().map_err(|_| ClientError::Poisoned)
+    }
+}
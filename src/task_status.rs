@@ -0,0 +1,144 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+use crate::config::status::Status;
+
+/// The answer to `GET_TASK_STATUS`. Servers that negotiated `extended-status` may add
+/// `progress: ` and `queue-position: ` headers; older ones only send the status line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskStatus {
+    pub status: Status,
+    pub progress: Option<Progress>,
+    pub queue_position: Option<u32>,
+}
+
+/// How far a running task has got, sent either as a fraction (`0.25`) or as rows (`256/1024`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Progress {
+    Fraction(f64),
+    Rows { done: u64, total: u64 },
+}
+
+impl TaskStatus {
+    pub fn new(status: Status) -> TaskStatus {
+        TaskStatus {
+            status,
+            progress: None,
+            queue_position: None,
+        }
+    }
+
+    /// Extrapolates the remaining time from the progress made since the task was started.
+    pub fn estimate_remaining(&self, elapsed: Duration) -> Option<Duration> {
+        let fraction = self.progress?.fraction();
+        if !fraction.is_finite() || fraction <= 0.0 || fraction > 1.0 {
+            return None;
+        }
+        // A tiny fraction extrapolates to more time than a `Duration` can hold.
+        Duration::try_from_secs_f64(elapsed.as_secs_f64() * (1.0 - fraction) / fraction).ok()
+    }
+}
+
+impl Progress {
+    pub fn fraction(&self) -> f64 {
+        match *self {
+            Progress::Fraction(fraction) => fraction,
+            Progress::Rows { total: 0, .. } => 0.0,
+            Progress::Rows { done, total } => done as f64 / total as f64,
+        }
+    }
+}
+
+impl fmt::Display for TaskStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.status)?;
+        if let Some(progress) = self.progress {
+            write!(f, " {:.1}%", progress.fraction() * 100.0)?;
+        }
+        if let Some(position) = self.queue_position {
+            write!(f, " queue-position: {}", position)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Progress::Fraction(fraction) => write!(f, "{}", fraction),
+            Progress::Rows { done, total } => write!(f, "{}/{}", done, total),
+        }
+    }
+}
+
+impl FromStr for Progress {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('/') {
+            Some((done, total)) => Ok(Progress::Rows {
+                done: done.trim().parse().map_err(|_| ())?,
+                total: total.trim().parse().map_err(|_| ())?,
+            }),
+            None => match s.parse::<f64>() {
+                Ok(fraction) if fraction.is_finite() => Ok(Progress::Fraction(fraction)),
+                _ => Err(()),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn running(progress: Progress) -> TaskStatus {
+        TaskStatus { progress: Some(progress), ..TaskStatus::new(Status::RUNNING) }
+    }
+
+    #[test]
+    fn parses_fractions_and_rows() {
+        assert_eq!("0.25".parse(), Ok(Progress::Fraction(0.25)));
+        assert_eq!("256 / 1024".parse(), Ok(Progress::Rows { done: 256, total: 1024 }));
+        assert_eq!(Progress::Rows { done: 256, total: 1024 }.fraction(), 0.25);
+    }
+
+    #[test]
+    fn rejects_non_finite_and_malformed_progress() {
+        for value in ["NaN", "inf", "-inf", "", "half", "1/", "/2", "-1/2"] {
+            assert_eq!(value.parse::<Progress>(), Err(()), "accepted '{}'", value);
+        }
+    }
+
+    #[test]
+    fn zero_total_rows_is_no_progress() {
+        let progress = Progress::Rows { done: 5, total: 0 };
+        assert_eq!(progress.fraction(), 0.0);
+        assert_eq!(running(progress).estimate_remaining(Duration::from_secs(10)), None);
+    }
+
+    #[test]
+    fn estimates_remaining_time() {
+        let status = running(Progress::Fraction(0.25));
+        assert_eq!(status.estimate_remaining(Duration::from_secs(10)), Some(Duration::from_secs(30)));
+        let done = running(Progress::Fraction(1.0));
+        assert_eq!(done.estimate_remaining(Duration::from_secs(10)), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn no_estimate_without_usable_progress() {
+        assert_eq!(TaskStatus::new(Status::RUNNING).estimate_remaining(Duration::from_secs(10)), None);
+        for fraction in [1.5, 0.0, -0.5, f64::NAN, f64::INFINITY] {
+            let status = running(Progress::Fraction(fraction));
+            assert_eq!(status.estimate_remaining(Duration::from_secs(10)), None, "estimated for {}", fraction);
+        }
+    }
+
+    #[test]
+    fn tiny_fraction_does_not_overflow() {
+        let status = running(Progress::Fraction(f64::MIN_POSITIVE));
+        assert_eq!(status.estimate_remaining(Duration::from_secs(10)), None);
+        let status = running(Progress::Rows { done: 1, total: u64::MAX });
+        assert_eq!(status.estimate_remaining(Duration::from_secs(u64::MAX / 2)), None);
+    }
+}